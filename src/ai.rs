@@ -1,15 +1,59 @@
 use openai_api_rs::v1::api::OpenAIClient;
 use openai_api_rs::v1::chat_completion::{self, ChatCompletionRequest, MessageRole, Content};
 use os_type;
+use crate::cache::{ResponseCache, cache_key};
+use crate::redact::Redactor;
 
+/// Describes the OS and shell commands will run in.
+pub fn platform_context() -> String {
+    let os = os_type::current_platform();
+    let shell = std::env::var("SHELL")
+        .ok()
+        .and_then(|path| path.rsplit('/').next().map(str::to_string))
+        .filter(|name| !name.is_empty());
+
+    match shell {
+        Some(shell) => format!("{:?} {}, {} shell", os.os_type, os.version, shell),
+        None => format!("{:?} {}", os.os_type, os.version),
+    }
+}
+
+/// Extracts the command from a ```` ```command: ...``` ```` response, or
+/// `None` when the response holds no single-line command.
+pub fn parse_command(response: &str) -> Option<String> {
+    let response = response.trim().trim_matches(['`', '\n', '\r']);
+    let command = response.replace("command: ", "").trim_start().to_string();
+    (!command.is_empty() && !command.contains('\n')).then_some(command)
+}
+
+/// Asks the model for a command. With a `cache`, the response for suggestion
+/// slot `index` is reused when the same prompt and examples were sent to the
+/// same model on the same platform before. Only responses `parse_command`
+/// accepts are cached or reused, so retrying after an invalid response
+/// always asks the model again. A cache that can't be read or written is
+/// skipped rather than failing the request.
 pub async fn get_command_suggestion(
     client: &OpenAIClient,
     model: &str,
+    redactor: &Redactor,
     user_input: &str,
+    examples: &[(String, String)],
+    cache: Option<&ResponseCache>,
+    index: usize,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    let os = os_type::current_platform();
-    
-    let platform_info = format!("The system the shell command wil be executed on is {:?} {}", os.os_type, os.version);
+    let context = platform_context();
+    let redacted_examples: Vec<(String, String)> = examples
+        .iter()
+        .map(|(description, command)| (redactor.redact(description), redactor.redact(command)))
+        .collect();
+    let key = cache_key(model, &context, &redactor.redact(user_input), &redacted_examples);
+    if let Some(cache) = cache {
+        if let Some(response) = cache.lookup(&key, index).filter(|r| parse_command(r).is_some()) {
+            return Ok(response);
+        }
+    }
+
+    let platform_info = format!("The system the shell command wil be executed on is {}", context);
     
     let system_message = format!(r#"
     You are an expert at using shell commands.
@@ -21,8 +65,23 @@ pub async fn get_command_suggestion(
     if the user asks to install Rust, respond with: ```command: curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh```;
     if the user asks to delete a directory, respond with: ```command: rm -rf /path/to/directory # add additional comments here if danger!```.
     "#, platform_info);
+
+    // Past requests the user ran successfully, as few-shot examples
+    let system_message = if redacted_examples.is_empty() {
+        system_message
+    } else {
+        let lines: Vec<String> = redacted_examples
+            .iter()
+            .map(|(description, command)| format!("    - \"{}\" => ```command: {}```", description, command))
+            .collect();
+        format!(
+            "{}\n    Commands this user ran before for similar requests (prefer their style and tools):\n{}\n",
+            system_message,
+            lines.join("\n")
+        )
+    };
     
-    let user_message = format!("Here's what I'm trying to do: {}", user_input);
+    let user_message = format!("Here's what I'm trying to do: {}", redactor.redact(user_input));
     
     let messages = vec![
         chat_completion::ChatCompletionMessage {
@@ -45,6 +104,11 @@ pub async fn get_command_suggestion(
     let result = client.chat_completion(req).await?;
     
     let suggestion = result.choices[0].message.content.clone().unwrap_or_default();
+    if let Some(cache) = cache {
+        if parse_command(&suggestion).is_some() {
+            cache.store(&key, index, &suggestion);
+        }
+    }
     Ok(suggestion)
 }
 
@@ -58,6 +122,7 @@ pub struct BookmarkMetadata {
 pub async fn generate_bookmark_info(
     client: &OpenAIClient,
     model: &str,
+    redactor: &Redactor,
     command: &str,
 ) -> Result<BookmarkMetadata, Box<dyn std::error::Error>> {
     let system_message = r#"
@@ -77,7 +142,7 @@ pub async fn generate_bookmark_info(
     Do not include any markdown code blocks, backticks, or any text outside the JSON object.
     "#.to_string();
     
-    let user_message = format!("Generate bookmark metadata for this command: {}", command);
+    let user_message = format!("Generate bookmark metadata for this command: {}", redactor.redact(command));
     
     let messages = vec![
         chat_completion::ChatCompletionMessage {
@@ -112,3 +177,40 @@ pub async fn generate_bookmark_info(
     Ok(metadata)
 }
 
+pub async fn explain_command(
+    client: &OpenAIClient,
+    model: &str,
+    redactor: &Redactor,
+    command: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let system_message = r#"
+    You are an expert at using shell commands.
+    Explain what the given shell command does, part by part, in a few short lines of plain text.
+    Point out anything destructive or irreversible. Do not use markdown.
+    "#.to_string();
+
+    let user_message = format!("Explain this command: {}", redactor.redact(command));
+
+    let messages = vec![
+        chat_completion::ChatCompletionMessage {
+            role: MessageRole::system,
+            content: Content::Text(system_message),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        },
+        chat_completion::ChatCompletionMessage {
+            role: MessageRole::user,
+            content: Content::Text(user_message),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        },
+    ];
+
+    let req = ChatCompletionRequest::new(model.to_string(), messages);
+    let result = client.chat_completion(req).await?;
+
+    let explanation = result.choices[0].message.content.clone().unwrap_or_default();
+    Ok(explanation.trim().to_string())
+}
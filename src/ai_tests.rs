@@ -0,0 +1,9 @@
+use crate::parse_command;
+
+#[test]
+fn test_parse_command() {
+    assert_eq!(parse_command("```command: ls -la```").as_deref(), Some("ls -la"));
+    assert_eq!(parse_command("  du -sh *\n").as_deref(), Some("du -sh *"));
+    assert_eq!(parse_command("```command: ```"), None);
+    assert_eq!(parse_command("```command: ls```\n\nThis lists the files."), None);
+}
@@ -1,36 +1,249 @@
+use std::cell::OnceCell;
 use std::path::PathBuf;
-use crate::storage::{Storage, get_data_dir};
+use crate::collection::{self, BookmarkSource};
+use crate::query::Query;
+use crate::storage::{Storage, Encryption, get_data_dir};
+use crate::template::{self, Placeholder};
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct BookmarkItem {
     pub name: String,
     pub command: String,
+    #[serde(default)]
     pub description: String,
+    #[serde(default)]
     pub tags: Vec<String>,
+    #[serde(default)]
     pub created_at: i64,
+    /// Number of times the bookmark was run with `shai bookmark run`
+    #[serde(default)]
+    pub use_count: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+    /// Program that runs the command as a script, e.g. `python3`; `None` runs
+    /// it in the shell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interpreter: Option<String>,
+    /// Free-form markdown notes
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+    /// Example outputs of the command
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<String>,
+    /// File of the shared collection the bookmark was read from; `None` for
+    /// personal bookmarks
+    #[serde(skip)]
+    pub source: Option<String>,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+impl BookmarkItem {
+    /// The `{{name}}` parameters of the command, see [`Placeholder`].
+    pub fn placeholders(&self) -> Result<Vec<Placeholder>, Box<dyn std::error::Error>> {
+        template::placeholders(&self.command)
+    }
+
+    /// Whether the command is a script run by an interpreter rather than a
+    /// shell command line.
+    pub fn is_script(&self) -> bool {
+        self.interpreter.is_some()
+    }
+}
+
+/// The hand-editable fields of a bookmark, as presented by `shai bookmark edit`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BookmarkEdit {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interpreter: Option<String>,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub examples: Vec<String>,
+}
+
+impl BookmarkEdit {
+    pub fn from_item(item: &BookmarkItem) -> Self {
+        Self {
+            name: item.name.clone(),
+            command: item.command.clone(),
+            description: item.description.clone(),
+            tags: item.tags.clone(),
+            interpreter: item.interpreter.clone(),
+            notes: item.notes.clone(),
+            examples: item.examples.clone(),
+        }
+    }
+
+    pub fn to_toml(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut edit: Self = toml::from_str(content).map_err(|e| format!("Invalid bookmark: {}", e))?;
+        edit.tags = normalize_tags(&edit.tags)?;
+        Ok(edit)
+    }
+
+    pub fn apply(self, item: &mut BookmarkItem) {
+        item.name = self.name;
+        item.command = self.command;
+        item.description = self.description;
+        item.tags = self.tags;
+        item.interpreter = self.interpreter.filter(|i| !i.trim().is_empty());
+        item.notes = self.notes;
+        item.examples = self.examples;
+    }
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct BookmarkData {
     pub bookmarks: Vec<BookmarkItem>,
 }
 
-impl Default for BookmarkData {
-    fn default() -> Self {
-        Self {
-            bookmarks: Vec::new(),
+/// What `Bookmark::import` does with a bookmark whose name is taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConflictPolicy {
+    /// Keep the existing bookmark
+    #[default]
+    Skip,
+    /// Import under a free `name-2`, `name-3`, ... name
+    Rename,
+    /// Replace the existing bookmark
+    Overwrite,
+}
+
+/// Outcome of `Bookmark::import`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub renamed: usize,
+    pub replaced: usize,
+    pub skipped: usize,
+}
+
+/// Selects bookmarks by tag. Tags are hierarchical: `k8s` also selects
+/// bookmarks tagged `k8s/logs`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagFilter {
+    pub tags: Vec<String>,
+    /// Select bookmarks with any of the tags instead of all of them
+    pub any: bool,
+}
+
+impl TagFilter {
+    /// Bookmarks carrying every one of `tags`.
+    pub fn all(tags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self { tags: tags.into_iter().map(Into::into).collect(), any: false }
+    }
+
+    /// Bookmarks carrying at least one of `tags`.
+    pub fn any(tags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self { tags: tags.into_iter().map(Into::into).collect(), any: true }
+    }
+
+    pub fn matches(&self, item: &BookmarkItem) -> bool {
+        let has = |filter: &String| item.tags.iter().any(|tag| tag_within(tag, filter.trim_matches('/')));
+        if self.tags.is_empty() {
+            true
+        } else if self.any {
+            self.tags.iter().any(has)
+        } else {
+            self.tags.iter().all(has)
+        }
+    }
+}
+
+/// Whether `tag` is `parent` or one of its `parent/...` children.
+pub fn tag_within(tag: &str, parent: &str) -> bool {
+    tag == parent || tag.strip_prefix(parent).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Trims a tag and its `/` separators, rejecting empty levels and whitespace.
+pub fn normalize_tag(tag: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let tag = tag.trim().trim_matches('/');
+    if tag.is_empty() || tag.split('/').any(|level| level.is_empty()) {
+        return Err(format!("Invalid tag '{}'", tag).into());
+    }
+    if tag.contains(|c: char| c.is_whitespace() || c == ',') {
+        return Err(format!("Invalid tag '{}': tags cannot contain spaces or commas", tag).into());
+    }
+    Ok(tag.to_string())
+}
+
+/// Normalizes each of `tags` with [`normalize_tag`], dropping repeats.
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = normalize_tag(tag)?;
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
         }
     }
+    Ok(normalized)
+}
+
+/// Turns free text, such as a tldr page title or a generated tag, into a
+/// tag by joining its words with `-`. Returns `None` if no valid tag is left.
+pub fn tag_from_text(text: &str) -> Option<String> {
+    let words: Vec<&str> = text.split(|c: char| c.is_whitespace() || c == ',').filter(|w| !w.is_empty()).collect();
+    normalize_tag(&words.join("-")).ok()
 }
 
-pub struct Bookmark;
+pub struct Bookmark {
+    path: PathBuf,
+    encryption: Option<Encryption>,
+    /// Read-only collections merged into listings and searches
+    sources: Vec<BookmarkSource>,
+    /// Bookmarks from `sources`, read once
+    shared: OnceCell<Vec<BookmarkItem>>,
+}
 
 impl Bookmark {
-    pub fn new() -> Self {
-        Self
+    /// Opens the bookmarks file in the shai data directory, encrypted if
+    /// configured through the environment, along with the shared collections
+    /// found by [`collection::sources_from_env`].
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut store = Self::personal()?;
+        store.sources = collection::sources_from_env()?;
+        Ok(store)
+    }
+
+    /// Like [`Bookmark::new`], without the shared collections.
+    pub fn personal() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut store = Self::with_path(get_data_dir()?.join("bookmarks.json"));
+        store.encryption = Encryption::from_env()?;
+        Ok(store)
+    }
+
+    /// Opens the bookmarks file at `path`.
+    pub fn with_path(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), encryption: None, sources: Vec::new(), shared: OnceCell::new() }
+    }
+
+    pub fn with_encryption(mut self, encryption: Encryption) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    pub fn with_sources(mut self, sources: Vec<BookmarkSource>) -> Self {
+        self.sources = sources;
+        self.shared = OnceCell::new();
+        self
+    }
+
+    pub fn sources(&self) -> &[BookmarkSource] {
+        &self.sources
     }
 
     pub fn add(&self, item: BookmarkItem) -> Result<(), Box<dyn std::error::Error>> {
+        item.placeholders()?;
         let mut data = self.load_data()?;
         
         // Check if bookmark with same name already exists
@@ -42,60 +255,283 @@ impl Bookmark {
         self.save(&data)
     }
 
-    pub fn list(&self, tag: Option<String>) -> Result<Vec<BookmarkItem>, Box<dyn std::error::Error>> {
-        let data = self.load_data()?;
-        
-        let bookmarks = if let Some(tag_filter) = tag {
-            data.bookmarks
-                .into_iter()
-                .filter(|b| b.tags.contains(&tag_filter))
-                .collect()
-        } else {
-            data.bookmarks
-        };
-        
-        Ok(bookmarks)
+    /// Personal bookmarks followed by those of the shared collections, limited
+    /// to those selected by `filter`.
+    pub fn list(&self, filter: &TagFilter) -> Result<Vec<BookmarkItem>, Box<dyn std::error::Error>> {
+        Ok(self.load_all()?.into_iter().filter(|b| filter.matches(b)).collect())
+    }
+
+    /// Every tag with the number of bookmarks carrying it or one of its
+    /// children, sorted by tag. Parents of hierarchical tags are included
+    /// even when no bookmark is tagged with them directly.
+    pub fn tags(&self) -> Result<Vec<(String, usize)>, Box<dyn std::error::Error>> {
+        let items = self.load_all()?;
+        let mut tags: Vec<String> = Vec::new();
+        for tag in items.iter().flat_map(|b| &b.tags) {
+            let levels: Vec<&str> = tag.split('/').collect();
+            for depth in 1..=levels.len() {
+                let prefix = levels[..depth].join("/");
+                if !tags.contains(&prefix) {
+                    tags.push(prefix);
+                }
+            }
+        }
+        tags.sort();
+
+        Ok(tags
+            .into_iter()
+            .map(|tag| {
+                let count = items.iter().filter(|b| b.tags.iter().any(|t| tag_within(t, &tag))).count();
+                (tag, count)
+            })
+            .collect())
+    }
+
+    /// Renames tag `from`, and its children, to `to` on personal bookmarks,
+    /// returning the number of bookmarks changed. Renaming onto a tag in use
+    /// is an error; use [`Bookmark::merge_tags`] for that.
+    pub fn rename_tag(&self, from: &str, to: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        let from = normalize_tag(from)?;
+        let to = normalize_tag(to)?;
+        if from == to {
+            return Err(format!("Tag '{}' is already named '{}'", from, to).into());
+        }
+        let in_use = self.load_all()?.iter().flat_map(|b| b.tags.clone()).any(|t| tag_within(&t, &to));
+        if in_use {
+            return Err(format!("Tag '{}' already exists; merge the tags instead", to).into());
+        }
+        self.retag(&[from], &to)
+    }
+
+    /// Replaces each of `tags`, and their children, with `into` on personal
+    /// bookmarks, returning the number of bookmarks changed.
+    pub fn merge_tags(&self, tags: &[String], into: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        let tags = normalize_tags(tags)?;
+        let into = normalize_tag(into)?;
+        let tags: Vec<String> = tags.into_iter().filter(|t| *t != into).collect();
+        if tags.is_empty() {
+            return Err("No tags to merge".into());
+        }
+        self.retag(&tags, &into)
+    }
+
+    fn retag(&self, from: &[String], to: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut data = self.load_data()?;
+        let mut changed = 0;
+        let now = chrono::Utc::now().timestamp();
+
+        for item in &mut data.bookmarks {
+            if !item.tags.iter().any(|t| from.iter().any(|f| tag_within(t, f))) {
+                continue;
+            }
+            let mut tags: Vec<String> = Vec::new();
+            for tag in &item.tags {
+                let tag = match from.iter().find(|f| tag_within(tag, f)) {
+                    // Tags already below the target stay, e.g. when merging `k8s` into `k8s/misc`
+                    Some(f) if !(tag_within(to, f) && tag_within(tag, to)) => format!("{}{}", to, &tag[f.len()..]),
+                    _ => tag.clone(),
+                };
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            item.tags = tags;
+            item.updated_at = Some(now);
+            changed += 1;
+        }
+
+        if changed == 0 {
+            // Tags only found in shared collections cannot be changed
+            if self.shared().iter().any(|b| b.tags.iter().any(|t| from.iter().any(|f| tag_within(t, f)))) {
+                return Err(format!("Tag '{}' is only used by read-only shared bookmarks", from.join("', '")).into());
+            }
+            return Err(format!("No bookmarks tagged '{}'", from.join("', '")).into());
+        }
+
+        self.save(&data)?;
+        Ok(changed)
     }
 
     pub fn get(&self, name: &str) -> Result<Option<BookmarkItem>, Box<dyn std::error::Error>> {
-        let data = self.load_data()?;
-        Ok(data.bookmarks.into_iter().find(|b| b.name == name))
+        Ok(self.load_all()?.into_iter().find(|b| b.name == name))
+    }
+
+    /// Returns `name` if no personal or shared bookmark uses it, otherwise
+    /// the first free `name-2`, `name-3`, ...
+    pub fn unique_name(&self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let items = self.load_all()?;
+        let taken = |candidate: &str| items.iter().any(|b| b.name == candidate);
+
+        if !taken(name) {
+            return Ok(name.to_string());
+        }
+        let suffix = (2..).find(|n| !taken(&format!("{}-{}", name, n))).unwrap_or_default();
+        Ok(format!("{}-{}", name, suffix))
+    }
+
+    /// Adds `items` in one write, resolving name clashes with existing
+    /// bookmarks and with each other according to `policy`. Items that repeat
+    /// an existing bookmark's command are always skipped.
+    pub fn import(
+        &self,
+        items: Vec<BookmarkItem>,
+        policy: ConflictPolicy,
+    ) -> Result<ImportSummary, Box<dyn std::error::Error>> {
+        let mut data = self.load_data()?;
+        let mut summary = ImportSummary::default();
+
+        for mut item in items {
+            item.placeholders()?;
+            if data.bookmarks.iter().any(|b| b.command == item.command) {
+                summary.skipped += 1;
+                continue;
+            }
+
+            match data.bookmarks.iter().position(|b| b.name == item.name) {
+                None => {
+                    data.bookmarks.push(item);
+                    summary.added += 1;
+                }
+                Some(_) if policy == ConflictPolicy::Skip => summary.skipped += 1,
+                Some(index) if policy == ConflictPolicy::Overwrite => {
+                    item.created_at = data.bookmarks[index].created_at;
+                    item.updated_at = Some(chrono::Utc::now().timestamp());
+                    data.bookmarks[index] = item;
+                    summary.replaced += 1;
+                }
+                Some(_) => {
+                    let taken = |candidate: &str| data.bookmarks.iter().any(|b| b.name == candidate);
+                    let suffix = (2..).find(|n| !taken(&format!("{}-{}", item.name, n))).unwrap_or_default();
+                    item.name = format!("{}-{}", item.name, suffix);
+                    data.bookmarks.push(item);
+                    summary.renamed += 1;
+                }
+            }
+        }
+
+        self.save(&data)?;
+        Ok(summary)
+    }
+
+    /// Applies `edit` to bookmark `name` and stamps `updated_at`, returning the
+    /// updated bookmark. Renaming onto an existing bookmark is an error.
+    pub fn update(
+        &self,
+        name: &str,
+        edit: impl FnOnce(&mut BookmarkItem),
+    ) -> Result<BookmarkItem, Box<dyn std::error::Error>> {
+        let mut data = self.load_data()?;
+        let index = match data.bookmarks.iter().position(|b| b.name == name) {
+            Some(index) => index,
+            None => return Err(self.not_personal(name).into()),
+        };
+
+        let mut item = data.bookmarks[index].clone();
+        edit(&mut item);
+        if item.name.trim().is_empty() {
+            return Err("Bookmark name cannot be empty".into());
+        }
+        if item.name != name && data.bookmarks.iter().any(|b| b.name == item.name) {
+            return Err(format!("Bookmark '{}' already exists", item.name).into());
+        }
+        item.placeholders()?;
+        item.updated_at = Some(chrono::Utc::now().timestamp());
+
+        data.bookmarks[index] = item.clone();
+        self.save(&data)?;
+        Ok(item)
     }
 
     pub fn remove(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
         let mut data = self.load_data()?;
+        if !data.bookmarks.iter().any(|b| b.name == name) {
+            if let Some(shared) = self.find_shared(name) {
+                return Err(read_only(shared).into());
+            }
+        }
         data.bookmarks.retain(|b| b.name != name);
         self.save(&data)
     }
 
+    /// Runs a search query (see `Query`), best matches first.
     pub fn search(&self, query: &str) -> Result<Vec<BookmarkItem>, Box<dyn std::error::Error>> {
-        let data = self.load_data()?;
-        let query_lower = query.to_lowercase();
-        
-        let results: Vec<BookmarkItem> = data.bookmarks
-            .into_iter()
-            .filter(|b| {
-                b.name.to_lowercase().contains(&query_lower)
-                    || b.command.to_lowercase().contains(&query_lower)
-                    || b.description.to_lowercase().contains(&query_lower)
-                    || b.tags.iter().any(|t| t.to_lowercase().contains(&query_lower))
-            })
-            .collect();
-        
-        Ok(results)
+        self.query(&Query::parse(query, false)?)
+    }
+
+    pub fn query(&self, query: &Query) -> Result<Vec<BookmarkItem>, Box<dyn std::error::Error>> {
+        Ok(query.rank(self.load_all()?))
+    }
+
+    /// Bumps the use count and last-used time of bookmark `name`. Shared
+    /// bookmarks are read-only, so their use is not tracked.
+    pub fn record_use(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut data = self.load_data()?;
+        let item = match data.bookmarks.iter_mut().find(|b| b.name == name) {
+            Some(item) => item,
+            None if self.find_shared(name).is_some() => return Ok(()),
+            None => return Err(format!("Bookmark '{}' not found", name).into()),
+        };
+        item.use_count += 1;
+        item.last_used = Some(chrono::Utc::now().timestamp());
+        self.save(&data)
+    }
+
+    fn load_all(&self) -> Result<Vec<BookmarkItem>, Box<dyn std::error::Error>> {
+        let mut items = self.load_data()?.bookmarks;
+        items.extend(self.shared().iter().cloned());
+        Ok(items)
+    }
+
+    /// Bookmarks from the shared collections. A collection that can't be
+    /// read is skipped with a warning, so it can't break personal bookmarks.
+    fn shared(&self) -> &[BookmarkItem] {
+        self.shared.get_or_init(|| {
+            let mut items = Vec::new();
+            for source in &self.sources {
+                match source.load() {
+                    Ok(loaded) => items.extend(loaded),
+                    Err(e) => eprintln!("Warning: skipping shared bookmarks {}: {}", source.path.display(), e),
+                }
+            }
+            items
+        })
+    }
+
+    fn find_shared(&self, name: &str) -> Option<&BookmarkItem> {
+        self.shared().iter().find(|b| b.name == name)
+    }
+
+    /// Error message for a bookmark missing from the personal file.
+    fn not_personal(&self, name: &str) -> String {
+        match self.find_shared(name) {
+            Some(shared) => read_only(shared),
+            None => format!("Bookmark '{}' not found", name),
+        }
     }
 
     fn load_data(&self) -> Result<BookmarkData, Box<dyn std::error::Error>> {
-        match self.load::<BookmarkData>() {
-            Ok(data) => Ok(data),
-            Err(_) => Ok(BookmarkData::default()),
+        if !self.get_storage_path().exists() {
+            return Ok(BookmarkData::default());
         }
+        self.load::<BookmarkData>()
     }
 }
 
+fn read_only(item: &BookmarkItem) -> String {
+    format!(
+        "Bookmark '{}' is shared from {} and is read-only",
+        item.name,
+        item.source.as_deref().unwrap_or("a collection")
+    )
+}
+
 impl Storage for Bookmark {
     fn get_storage_path(&self) -> PathBuf {
-        get_data_dir().join("bookmarks.json")
+        self.path.clone()
+    }
+
+    fn get_encryption(&self) -> Option<&Encryption> {
+        self.encryption.as_ref()
     }
 }
 
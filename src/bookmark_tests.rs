@@ -0,0 +1,246 @@
+use crate::{Bookmark, BookmarkEdit, BookmarkItem, Encryption, TagFilter};
+use crate::bookmark::{normalize_tags, tag_from_text};
+use tempfile::TempDir;
+
+// Helper function to create a test bookmark store backed by a temporary file
+fn setup_test_bookmark() -> (Bookmark, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let bookmark = Bookmark::with_path(temp_dir.path().join("bookmarks.json"));
+
+    (bookmark, temp_dir)
+}
+
+fn item(name: &str, command: &str, tags: &[&str]) -> BookmarkItem {
+    BookmarkItem {
+        name: name.to_string(),
+        command: command.to_string(),
+        description: String::new(),
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        created_at: 0,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_add_and_get_bookmark() {
+    let (bookmark, _temp_dir) = setup_test_bookmark();
+
+    bookmark.add(item("docker-clean", "docker system prune -af", &["docker"])).unwrap();
+
+    let found = bookmark.get("docker-clean").unwrap();
+    assert_eq!(found.unwrap().command, "docker system prune -af");
+}
+
+#[test]
+fn test_add_duplicate_bookmark() {
+    let (bookmark, _temp_dir) = setup_test_bookmark();
+
+    bookmark.add(item("list", "ls -la", &[])).unwrap();
+    assert!(bookmark.add(item("list", "ls", &[])).is_err());
+}
+
+#[test]
+fn test_list_by_tag() {
+    let (bookmark, _temp_dir) = setup_test_bookmark();
+
+    bookmark.add(item("docker-clean", "docker system prune -af", &["docker"])).unwrap();
+    bookmark.add(item("list", "ls -la", &["files"])).unwrap();
+
+    let items = bookmark.list(&TagFilter::all(["docker"])).unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].name, "docker-clean");
+}
+
+#[test]
+fn test_remove_bookmark() {
+    let (bookmark, _temp_dir) = setup_test_bookmark();
+
+    bookmark.add(item("list", "ls -la", &[])).unwrap();
+    bookmark.remove("list").unwrap();
+
+    assert!(bookmark.get("list").unwrap().is_none());
+}
+
+#[test]
+fn test_record_use() {
+    let (bookmark, _temp_dir) = setup_test_bookmark();
+
+    bookmark.add(item("list", "ls -la", &[])).unwrap();
+    bookmark.record_use("list").unwrap();
+    bookmark.record_use("list").unwrap();
+
+    let found = bookmark.get("list").unwrap().unwrap();
+    assert_eq!(found.use_count, 2);
+    assert!(found.last_used.is_some());
+    assert!(bookmark.record_use("missing").is_err());
+}
+
+#[test]
+fn test_update_bookmark() {
+    let (bookmark, _temp_dir) = setup_test_bookmark();
+
+    bookmark.add(item("list", "ls -la", &["files"])).unwrap();
+    bookmark.add(item("disk", "df -h", &[])).unwrap();
+
+    let updated = bookmark.update("list", |b| {
+        b.name = "list-all".to_string();
+        b.command = "ls -lah".to_string();
+    }).unwrap();
+    assert!(updated.updated_at.is_some());
+    assert!(bookmark.get("list").unwrap().is_none());
+    assert_eq!(bookmark.get("list-all").unwrap().unwrap().command, "ls -lah");
+
+    assert!(bookmark.update("disk", |b| b.name = "list-all".to_string()).is_err());
+    assert!(bookmark.update("disk", |b| b.command = "echo {{env:dev|prod=test}}".to_string()).is_err());
+    assert!(bookmark.update("missing", |_| {}).is_err());
+}
+
+#[test]
+fn test_bookmark_edit_toml_roundtrip() {
+    let mut original = item("greet", "echo \"hi\"\necho bye", &["demo"]);
+    original.description = "Say hi".to_string();
+
+    let edit = BookmarkEdit::from_item(&original);
+    let parsed = BookmarkEdit::from_toml(&edit.to_toml().unwrap()).unwrap();
+    assert_eq!(parsed, edit);
+
+    let parsed = BookmarkEdit::from_toml("name = \"hello\"\ncommand = \"echo hello\"\n").unwrap();
+    parsed.apply(&mut original);
+    assert_eq!(original.name, "hello");
+    assert!(original.tags.is_empty());
+    assert!(BookmarkEdit::from_toml("name = ").is_err());
+}
+
+#[test]
+fn test_normalized_tags_filter() {
+    let (bookmark, _temp_dir) = setup_test_bookmark();
+
+    let tags = normalize_tags(&[" k8s/logs/ ".to_string(), "ops".to_string(), "k8s/logs".to_string()]).unwrap();
+    assert_eq!(tags, ["k8s/logs", "ops"]);
+    assert!(normalize_tags(&["a b".to_string()]).is_err());
+    assert_eq!(tag_from_text(" Git  commit ").as_deref(), Some("Git-commit"));
+    assert_eq!(tag_from_text(" / "), None);
+
+    let mut logs = item("pod-logs", "kubectl logs", &[]);
+    logs.tags = tags;
+    bookmark.add(logs).unwrap();
+    let edit = BookmarkEdit::from_toml("name = \"top\"\ncommand = \"kubectl top\"\ntags = [\" /k8s \"]\n").unwrap();
+    let mut top = item("top", "", &[]);
+    edit.apply(&mut top);
+    bookmark.add(top).unwrap();
+    assert!(BookmarkEdit::from_toml("name = \"x\"\ncommand = \"x\"\ntags = [\"a b\"]\n").is_err());
+
+    let names = |tag: &str| -> Vec<String> {
+        bookmark.list(&TagFilter::all([tag])).unwrap().into_iter().map(|b| b.name).collect()
+    };
+    assert_eq!(names("k8s/logs"), ["pod-logs"]);
+    assert_eq!(names("k8s"), ["pod-logs", "top"]);
+}
+
+#[test]
+fn test_script_bookmark_fields() {
+    let (bookmark, _temp_dir) = setup_test_bookmark();
+
+    let mut script = item("greet", "import sys\nprint('hi')\n", &[]);
+    script.interpreter = Some("python3".to_string());
+    script.notes = "# Greeting\nSays *hi*.".to_string();
+    script.examples = vec!["hi".to_string()];
+    bookmark.add(script.clone()).unwrap();
+
+    let stored = bookmark.get("greet").unwrap().unwrap();
+    assert!(stored.is_script());
+    assert_eq!(stored.notes, script.notes);
+    assert_eq!(stored.examples, ["hi"]);
+
+    let edit = BookmarkEdit::from_item(&stored);
+    let mut parsed = BookmarkEdit::from_toml(&edit.to_toml().unwrap()).unwrap();
+    assert_eq!(parsed, edit);
+
+    // An empty interpreter turns the script back into a command line
+    parsed.interpreter = Some(String::new());
+    let mut edited = stored.clone();
+    parsed.apply(&mut edited);
+    assert!(!edited.is_script());
+}
+
+#[test]
+fn test_unique_name() {
+    let (bookmark, _temp_dir) = setup_test_bookmark();
+
+    assert_eq!(bookmark.unique_name("list").unwrap(), "list");
+    bookmark.add(item("list", "ls", &[])).unwrap();
+    bookmark.add(item("list-2", "ls -l", &[])).unwrap();
+    assert_eq!(bookmark.unique_name("list").unwrap(), "list-3");
+}
+
+#[test]
+fn test_list_by_hierarchical_tags() {
+    let (bookmark, _temp_dir) = setup_test_bookmark();
+
+    bookmark.add(item("pod-logs", "kubectl logs -f {{pod}}", &["k8s/logs", "debug"])).unwrap();
+    bookmark.add(item("pods", "kubectl get pods", &["k8s"])).unwrap();
+    bookmark.add(item("k8sx", "k8sx", &["k8sx"])).unwrap();
+    bookmark.add(item("journal", "journalctl -f", &["logs"])).unwrap();
+
+    let names = |filter: TagFilter| -> Vec<String> {
+        bookmark.list(&filter).unwrap().into_iter().map(|b| b.name).collect()
+    };
+    assert_eq!(names(TagFilter::all(["k8s"])), ["pod-logs", "pods"]);
+    assert_eq!(names(TagFilter::all(["k8s", "debug"])), ["pod-logs"]);
+    assert_eq!(names(TagFilter::any(["k8s/logs", "logs"])), ["pod-logs", "journal"]);
+    assert_eq!(names(TagFilter::default()).len(), 4);
+}
+
+#[test]
+fn test_tag_counts() {
+    let (bookmark, _temp_dir) = setup_test_bookmark();
+
+    bookmark.add(item("pod-logs", "kubectl logs -f", &["k8s/logs"])).unwrap();
+    bookmark.add(item("pods", "kubectl get pods", &["k8s"])).unwrap();
+
+    assert_eq!(
+        bookmark.tags().unwrap(),
+        [("k8s".to_string(), 2), ("k8s/logs".to_string(), 1)]
+    );
+}
+
+#[test]
+fn test_rename_and_merge_tags() {
+    let (bookmark, _temp_dir) = setup_test_bookmark();
+
+    bookmark.add(item("pod-logs", "kubectl logs -f", &["k8s/logs", "kube"])).unwrap();
+    bookmark.add(item("pods", "kubectl get pods", &["k8s"])).unwrap();
+    bookmark.add(item("list", "ls", &["files"])).unwrap();
+
+    assert!(bookmark.rename_tag("k8s", "kube").is_err());
+    assert!(bookmark.rename_tag("missing", "other").is_err());
+    assert_eq!(bookmark.rename_tag("k8s", "kubernetes").unwrap(), 2);
+    let pod_logs = bookmark.get("pod-logs").unwrap().unwrap();
+    assert_eq!(pod_logs.tags, ["kubernetes/logs", "kube"]);
+    assert!(pod_logs.updated_at.is_some());
+
+    assert_eq!(bookmark.merge_tags(&["kube".to_string(), "kubernetes/logs".to_string()], "kubernetes").unwrap(), 1);
+    assert_eq!(bookmark.get("pod-logs").unwrap().unwrap().tags, ["kubernetes"]);
+    assert_eq!(bookmark.get("list").unwrap().unwrap().tags, ["files"]);
+    assert!(bookmark.merge_tags(&["a b".to_string()], "c").is_err());
+}
+
+#[test]
+fn test_encrypted_bookmarks() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("bookmarks.json");
+
+    // Plaintext bookmarks are read and encrypted on the next write
+    Bookmark::with_path(&path).add(item("token", "echo s3cr3t", &[])).unwrap();
+    let bookmark = Bookmark::with_path(&path).with_encryption(Encryption::from_passphrase("correct horse"));
+    assert_eq!(bookmark.get("token").unwrap().unwrap().command, "echo s3cr3t");
+    bookmark.add(item("list", "ls", &[])).unwrap();
+
+    let raw = std::fs::read_to_string(&path).unwrap();
+    assert!(!raw.contains("s3cr3t"));
+    assert_eq!(bookmark.list(&TagFilter::default()).unwrap().len(), 2);
+
+    let wrong = Bookmark::with_path(&path).with_encryption(Encryption::from_passphrase("battery staple"));
+    assert!(wrong.get("token").is_err());
+    assert!(Bookmark::with_path(&path).get("token").is_err());
+}
@@ -0,0 +1,212 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::config::env_number;
+use crate::storage::{Storage, Encryption, get_data_dir};
+
+const DEFAULT_TTL_HOURS: i64 = 7 * 24;
+const DEFAULT_MAX_ENTRIES: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub key: String,
+    /// One response per suggestion slot, so repeated requests still offer
+    /// several distinct suggestions
+    pub responses: Vec<String>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheData {
+    entries: Vec<CacheEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub responses: usize,
+    pub expired: usize,
+    pub oldest: Option<i64>,
+    pub newest: Option<i64>,
+    pub path: PathBuf,
+}
+
+/// Local cache of model responses, stored next to the history file.
+///
+/// Entries expire after a TTL, and the oldest entries are dropped once the
+/// cache holds more than `max_entries` keys.
+pub struct ResponseCache {
+    path: PathBuf,
+    encryption: Option<Encryption>,
+    ttl_secs: i64,
+    max_entries: usize,
+    verbose: bool,
+}
+
+impl Storage for ResponseCache {
+    fn get_storage_path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    fn get_encryption(&self) -> Option<&Encryption> {
+        self.encryption.as_ref()
+    }
+}
+
+impl ResponseCache {
+    /// Opens the cache in the data directory. Reads `SHAI_CACHE_TTL_HOURS`
+    /// (default 168) and `SHAI_CACHE_MAX_ENTRIES` (default 500).
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut cache = Self::with_path(get_data_dir()?.join("cache.json"));
+        cache.encryption = Encryption::from_env()?;
+        if let Some(hours) = env_number::<i64>("SHAI_CACHE_TTL_HOURS")? {
+            cache.ttl_secs = hours * 60 * 60;
+        }
+        if let Some(max) = env_number("SHAI_CACHE_MAX_ENTRIES")? {
+            cache.max_entries = max;
+        }
+        Ok(cache)
+    }
+
+    /// Opens the cache file at `path`.
+    pub fn with_path(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            encryption: None,
+            ttl_secs: DEFAULT_TTL_HOURS * 60 * 60,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            verbose: false,
+        }
+    }
+
+    pub fn with_encryption(mut self, encryption: Encryption) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    pub fn with_ttl(mut self, ttl_secs: i64) -> Self {
+        self.ttl_secs = ttl_secs;
+        self
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Prints cache errors that `lookup` and `store` otherwise ignore.
+    pub fn with_verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Like `get`, but an unreadable cache counts as a miss.
+    pub fn lookup(&self, key: &str, index: usize) -> Option<String> {
+        self.get(key, index).unwrap_or_else(|e| {
+            if self.verbose {
+                eprintln!("Warning: ignoring response cache {}: {}", self.path.display(), e);
+            }
+            None
+        })
+    }
+
+    /// Like `put`, but a failure only skips caching the response.
+    pub fn store(&self, key: &str, index: usize, response: &str) {
+        if let Err(e) = self.put(key, index, response) {
+            if self.verbose {
+                eprintln!("Warning: not caching response in {}: {}", self.path.display(), e);
+            }
+        }
+    }
+
+    /// Returns the cached response for `key` in suggestion slot `index`, if
+    /// fresh. Reading never writes the cache file.
+    pub fn get(&self, key: &str, index: usize) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let data = self.load_data()?;
+        let now = chrono::Utc::now().timestamp();
+
+        Ok(data.entries
+            .iter()
+            .find(|e| e.key == key && !self.is_expired(e, now))
+            .and_then(|e| e.responses.get(index).cloned()))
+    }
+
+    /// Stores `response` for `key` in suggestion slot `index`. Slots fill in
+    /// order, so `index` may be at most one past the last stored slot.
+    pub fn put(&self, key: &str, index: usize, response: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut data = self.load_data()?;
+        let now = chrono::Utc::now().timestamp();
+        data.entries.retain(|e| !self.is_expired(e, now));
+
+        let position = match data.entries.iter().position(|e| e.key == key) {
+            Some(position) => position,
+            None => {
+                data.entries.push(CacheEntry {
+                    key: key.to_string(),
+                    responses: Vec::new(),
+                    created_at: now,
+                });
+                data.entries.len() - 1
+            }
+        };
+        let responses = &mut data.entries[position].responses;
+        match index.cmp(&responses.len()) {
+            std::cmp::Ordering::Less => responses[index] = response.to_string(),
+            std::cmp::Ordering::Equal => responses.push(response.to_string()),
+            std::cmp::Ordering::Greater => {
+                return Err(format!("Cannot cache suggestion slot {}: slot {} is empty", index, responses.len()).into());
+            }
+        }
+
+        if data.entries.len() > self.max_entries {
+            data.entries.sort_by_key(|e| e.created_at);
+            let excess = data.entries.len() - self.max_entries;
+            data.entries.drain(..excess);
+        }
+
+        self.save(&data)
+    }
+
+    pub fn stats(&self) -> Result<CacheStats, Box<dyn std::error::Error>> {
+        let data = self.load_data()?;
+        let now = chrono::Utc::now().timestamp();
+
+        Ok(CacheStats {
+            entries: data.entries.len(),
+            responses: data.entries.iter().map(|e| e.responses.len()).sum(),
+            expired: data.entries.iter().filter(|e| self.is_expired(e, now)).count(),
+            oldest: data.entries.iter().map(|e| e.created_at).min(),
+            newest: data.entries.iter().map(|e| e.created_at).max(),
+            path: self.path.clone(),
+        })
+    }
+
+    /// Removes every cached response, returning the number of entries removed.
+    pub fn clear(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let data = self.load_data()?;
+        self.save(&CacheData::default())?;
+        Ok(data.entries.len())
+    }
+
+    fn is_expired(&self, entry: &CacheEntry, now: i64) -> bool {
+        now - entry.created_at >= self.ttl_secs
+    }
+
+    fn load_data(&self) -> Result<CacheData, Box<dyn std::error::Error>> {
+        if !self.get_storage_path().exists() {
+            return Ok(CacheData::default());
+        }
+        self.load::<CacheData>()
+    }
+}
+
+/// Builds a cache key from the model, platform context, prompt and the
+/// few-shot examples sent with it. The prompt is lowercased and its whitespace
+/// collapsed so trivial differences still hit.
+pub fn cache_key(model: &str, context: &str, prompt: &str, examples: &[(String, String)]) -> String {
+    let prompt = prompt.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let mut key = format!("{}\n{}\n{}", model, context, prompt);
+    for (description, command) in examples {
+        key.push_str(&format!("\n{}\t{}", description, command));
+    }
+    key
+}
@@ -0,0 +1,98 @@
+use crate::{Encryption, ResponseCache};
+use crate::cache::cache_key;
+use tempfile::TempDir;
+
+fn setup_test_cache() -> (ResponseCache, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let cache = ResponseCache::with_path(temp_dir.path().join("cache.json"));
+    (cache, temp_dir)
+}
+
+#[test]
+fn test_cache_key_normalizes_prompt() {
+    assert_eq!(
+        cache_key("model", "Linux", "  List   Files ", &[]),
+        cache_key("model", "Linux", "list files", &[])
+    );
+    assert_ne!(cache_key("model", "Linux", "list files", &[]), cache_key("other", "Linux", "list files", &[]));
+    assert_ne!(cache_key("model", "Linux", "list files", &[]), cache_key("model", "Macos", "list files", &[]));
+
+    let examples = vec![("list files".to_string(), "exa -l".to_string())];
+    assert_ne!(cache_key("model", "Linux", "list files", &[]), cache_key("model", "Linux", "list files", &examples));
+}
+
+#[test]
+fn test_cache_hits_per_slot() {
+    let (cache, _temp_dir) = setup_test_cache();
+
+    assert_eq!(cache.get("key", 0).unwrap(), None);
+    cache.put("key", 0, "ls").unwrap();
+    cache.put("key", 1, "ls -la").unwrap();
+
+    assert_eq!(cache.get("key", 0).unwrap().as_deref(), Some("ls"));
+    assert_eq!(cache.get("key", 1).unwrap().as_deref(), Some("ls -la"));
+    assert_eq!(cache.get("key", 2).unwrap(), None);
+
+    // Slots fill in order, so a response can't land in the wrong slot
+    assert!(cache.put("key", 3, "ls -l").is_err());
+    assert!(cache.put("other", 1, "ls -l").is_err());
+    assert_eq!(cache.get("key", 2).unwrap(), None);
+
+    let stats = cache.stats().unwrap();
+    assert_eq!((stats.entries, stats.responses), (1, 2));
+
+    assert_eq!(cache.clear().unwrap(), 1);
+    assert_eq!(cache.get("key", 0).unwrap(), None);
+}
+
+#[test]
+fn test_cache_get_does_not_write() {
+    let (cache, temp_dir) = setup_test_cache();
+    let path = temp_dir.path().join("cache.json");
+
+    cache.put("key", 0, "ls").unwrap();
+    let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    assert_eq!(cache.get("key", 0).unwrap().as_deref(), Some("ls"));
+    assert_eq!(std::fs::metadata(&path).unwrap().modified().unwrap(), modified);
+}
+
+#[test]
+fn test_cache_ttl_and_max_entries() {
+    let (cache, _temp_dir) = setup_test_cache();
+
+    let expired = cache.with_ttl(0);
+    expired.put("key", 0, "ls").unwrap();
+    assert_eq!(expired.get("key", 0).unwrap(), None);
+
+    let (cache, _temp_dir) = setup_test_cache();
+    let cache = cache.with_max_entries(2);
+    for key in ["a", "b", "c"] {
+        cache.put(key, 0, key).unwrap();
+    }
+    assert_eq!(cache.stats().unwrap().entries, 2);
+}
+
+#[test]
+fn test_encrypted_cache() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("cache.json");
+    let cache = ResponseCache::with_path(&path).with_encryption(Encryption::from_passphrase("correct horse"));
+
+    cache.put("key", 0, "du -sh * | sort -h").unwrap();
+    assert!(!std::fs::read_to_string(&path).unwrap().contains("sort -h"));
+    assert_eq!(cache.get("key", 0).unwrap().as_deref(), Some("du -sh * | sort -h"));
+    assert!(ResponseCache::with_path(&path).get("key", 0).is_err());
+}
+
+#[test]
+fn test_unreadable_cache_is_a_miss() {
+    let (cache, temp_dir) = setup_test_cache();
+    let path = temp_dir.path().join("cache.json");
+    std::fs::write(&path, "not json").unwrap();
+
+    assert!(cache.get("key", 0).is_err());
+    assert_eq!(cache.lookup("key", 0), None);
+    cache.store("key", 0, "ls");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "not json");
+}
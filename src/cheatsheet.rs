@@ -0,0 +1,278 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::bookmark::{tag_from_text, BookmarkItem};
+use crate::storage::{home_dir, get_data_home};
+use crate::template;
+
+/// Cheat sheet formats `shai bookmark import` understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CheatFormat {
+    /// navi `.cheat` files
+    Navi,
+    /// pet snippet TOML
+    Pet,
+    /// tldr-pages markdown
+    Tldr,
+}
+
+impl CheatFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CheatFormat::Navi => "navi",
+            CheatFormat::Pet => "pet",
+            CheatFormat::Tldr => "tldr",
+        }
+    }
+
+    /// Where the tool keeps its cheat sheets by default, if it has such a place.
+    pub fn default_path(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        match self {
+            CheatFormat::Navi => Ok(get_data_home()?.join("navi").join("cheats")),
+            CheatFormat::Pet => {
+                // pet uses ~/.config/pet regardless of platform
+                let home = home_dir().ok_or("Cannot determine home directory")?;
+                Ok(home.join(".config").join("pet").join("snippet.toml"))
+            }
+            CheatFormat::Tldr => Err("tldr pages have no default location: pass a page or a pages directory".into()),
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            CheatFormat::Navi => "cheat",
+            CheatFormat::Pet => "toml",
+            CheatFormat::Tldr => "md",
+        }
+    }
+
+    /// Converts cheat sheet contents into bookmarks. `file_stem` names tldr
+    /// pages that lack a title.
+    pub fn parse(&self, content: &str, file_stem: &str) -> Result<Vec<BookmarkItem>, Box<dyn std::error::Error>> {
+        let items = match self {
+            CheatFormat::Navi => parse_navi(content),
+            CheatFormat::Pet => parse_pet(content)?,
+            CheatFormat::Tldr => parse_tldr(content, file_stem),
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        Ok(items
+            .into_iter()
+            .map(|mut item| {
+                item.created_at = now;
+                // Cheat sheet tags are free text, so spaces become dashes
+                let mut tags: Vec<String> = Vec::new();
+                for tag in item.tags.iter().filter_map(|t| tag_from_text(t)) {
+                    if !tags.contains(&tag) {
+                        tags.push(tag);
+                    }
+                }
+                item.tags = tags;
+                item
+            })
+            .collect())
+    }
+
+    /// Reads a cheat sheet file, or every matching file below a directory.
+    pub fn read(&self, path: &Path) -> Result<Vec<BookmarkItem>, Box<dyn std::error::Error>> {
+        let mut files = Vec::new();
+        collect_files(path, self.extension(), &mut files)?;
+        files.sort();
+
+        let mut items = Vec::new();
+        for file in files {
+            let content = fs::read_to_string(&file)
+                .map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+            let stem = file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            items.extend(self.parse(&content, &stem)
+                .map_err(|e| format!("Cannot import {}: {}", file.display(), e))?);
+        }
+        Ok(items)
+    }
+}
+
+fn collect_files(path: &Path, extension: &str, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    if !path.is_dir() {
+        if !path.exists() {
+            return Err(format!("{} does not exist", path.display()).into());
+        }
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let entries = fs::read_dir(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    for entry in entries {
+        let entry = entry?.path();
+        if entry.is_dir() {
+            collect_files(&entry, extension, files)?;
+        } else if entry.extension().is_some_and(|e| e == extension) {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// navi: `% tag, tag` headers, `# description` lines each followed by a
+/// command (continued with a trailing `\`), and `<variable>` placeholders.
+/// `$ variable: ...` suggestion commands and `;` comments are dropped.
+fn parse_navi(content: &str) -> Vec<BookmarkItem> {
+    let mut items = Vec::new();
+    let mut tags: Vec<String> = Vec::new();
+    let mut description = String::new();
+    let mut command: Option<String> = None;
+
+    let mut finish = |command: &mut Option<String>, description: &str, tags: &[String]| {
+        if let Some(command) = command.take() {
+            items.push(BookmarkItem {
+                name: slug(if description.is_empty() { &command } else { description }),
+                command: convert_angle_placeholders(&command),
+                description: description.to_string(),
+                tags: tags.to_vec(),
+                ..Default::default()
+            });
+        }
+    };
+
+    for line in content.lines() {
+        let continuing = command.as_ref().is_some_and(|c| c.ends_with('\\'));
+        if continuing {
+            if let Some(command) = command.as_mut() {
+                command.push('\n');
+                command.push_str(line);
+            }
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix('%') {
+            finish(&mut command, &description, &tags);
+            tags = rest.split(',').map(|t| t.trim().to_string()).collect();
+            description.clear();
+        } else if let Some(rest) = trimmed.strip_prefix('#') {
+            finish(&mut command, &description, &tags);
+            description = rest.trim().to_string();
+        } else if trimmed.is_empty() {
+            finish(&mut command, &description, &tags);
+            description.clear();
+        } else if trimmed.starts_with('$') || trimmed.starts_with(';') || trimmed.starts_with('@') {
+            continue;
+        } else {
+            match command.as_mut() {
+                // Consecutive lines form one multi-line command
+                Some(command) => {
+                    command.push('\n');
+                    command.push_str(line);
+                }
+                None => command = Some(line.to_string()),
+            }
+        }
+    }
+    finish(&mut command, &description, &tags);
+
+    items
+}
+
+#[derive(serde::Deserialize)]
+struct PetFile {
+    #[serde(default)]
+    snippets: Vec<PetSnippet>,
+}
+
+#[derive(serde::Deserialize)]
+struct PetSnippet {
+    #[serde(default)]
+    description: String,
+    command: String,
+    #[serde(default)]
+    tag: Vec<String>,
+}
+
+/// pet: `[[snippets]]` tables with `<param>` or `<param=default>` placeholders.
+fn parse_pet(content: &str) -> Result<Vec<BookmarkItem>, Box<dyn std::error::Error>> {
+    let file: PetFile = toml::from_str(content).map_err(|e| format!("Invalid pet snippets: {}", e))?;
+
+    Ok(file.snippets
+        .into_iter()
+        .map(|snippet| BookmarkItem {
+            name: slug(if snippet.description.is_empty() { &snippet.command } else { &snippet.description }),
+            command: convert_angle_placeholders(&snippet.command),
+            description: snippet.description,
+            tags: snippet.tag,
+            ..Default::default()
+        })
+        .collect())
+}
+
+/// tldr: a `# page` title, `- description:` lines each followed by a
+/// backticked example whose `{{placeholders}}` are free text.
+fn parse_tldr(content: &str, file_stem: &str) -> Vec<BookmarkItem> {
+    let mut items = Vec::new();
+    let mut page = file_stem.to_string();
+    let mut description = String::new();
+
+    for line in content.lines().map(str::trim) {
+        if let Some(title) = line.strip_prefix("# ") {
+            page = title.trim().to_string();
+        } else if let Some(rest) = line.strip_prefix("- ") {
+            // Drop the mnemonic brackets of `[c]reate`-style descriptions
+            description = rest.trim().trim_end_matches(':').replace(['[', ']'], "");
+        } else if line.len() > 1 && line.starts_with('`') && line.ends_with('`') {
+            let example = &line[1..line.len() - 1];
+            items.push(BookmarkItem {
+                name: slug(&format!("{} {}", page, description)),
+                command: convert_tldr_placeholders(example),
+                description: description.clone(),
+                tags: vec![page.clone(), "tldr".to_string()],
+                ..Default::default()
+            });
+        }
+    }
+
+    items
+}
+
+/// Rewrites navi/pet `<name>` and `<name=default>` variables as `{{name}}`
+/// placeholders. Anything else in angle brackets, like redirections, is kept.
+fn convert_angle_placeholders(command: &str) -> String {
+    let escaped = template::escape_literal(command);
+    let pattern = regex::Regex::new(r"<([A-Za-z_][A-Za-z0-9_-]*)(=[^<>]*)?>").expect("valid regex");
+    pattern.replace_all(&escaped, "{{$1$2}}").to_string()
+}
+
+/// tldr placeholders hold example text such as `{{path/to/file}}`; turn the
+/// text into a placeholder name.
+fn convert_tldr_placeholders(command: &str) -> String {
+    let mut result = String::with_capacity(command.len());
+    let mut rest = command;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else { break };
+        result.push_str(&template::escape_literal(&rest[..start]));
+        let name = placeholder_name(&rest[start + 2..start + end]);
+        result.push_str(&format!("{{{{{}}}}}", name));
+        rest = &rest[start + end + 2..];
+    }
+    result.push_str(&template::escape_literal(rest));
+    result
+}
+
+fn placeholder_name(text: &str) -> String {
+    let name: String = text
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let name = name.split('_').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("_");
+    if name.is_empty() { "value".to_string() } else { name }
+}
+
+/// Kebab-case bookmark name from the first few words of `text`.
+fn slug(text: &str) -> String {
+    let words: Vec<String> = text
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .take(6)
+        .map(str::to_string)
+        .collect();
+    if words.is_empty() { "imported".to_string() } else { words.join("-") }
+}
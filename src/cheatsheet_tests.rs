@@ -0,0 +1,120 @@
+use crate::{Bookmark, BookmarkItem, CheatFormat, ConflictPolicy, ImportSummary, TagFilter};
+use tempfile::TempDir;
+
+const NAVI: &str = r#"% git, code
+
+# Change branch
+git checkout <branch>
+
+# Show file at revision
+git show <rev=HEAD>:<file> \
+  | less
+
+$ branch: git branch | awk '{print $NF}'
+; a comment
+"#;
+
+const PET: &str = r#"
+[[snippets]]
+  description = "Ping google"
+  command = "ping -c <count=3> 8.8.8.8 > /dev/null"
+  tag = ["network"]
+  output = ""
+"#;
+
+const TLDR: &str = r#"# tar
+
+> Archiving utility.
+> More information: <https://www.gnu.org/software/tar>.
+
+- [c]reate an archive from files:
+
+`tar cf {{path/to/target.tar}} {{path/to/file1 path/to/file2 ...}}`
+
+- E[x]tract an archive:
+
+`tar xf {{source.tar}}`
+"#;
+
+#[test]
+fn test_parse_navi() {
+    let items = CheatFormat::Navi.parse(NAVI, "git").unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].name, "change-branch");
+    assert_eq!(items[0].command, "git checkout {{branch}}");
+    assert_eq!(items[0].tags, vec!["git", "code"]);
+    assert_eq!(items[1].command, "git show {{rev=HEAD}}:{{file}} \\\n  | less");
+    assert_eq!(items[1].placeholders().unwrap().len(), 2);
+}
+
+#[test]
+fn test_import_keeps_literal_braces() {
+    let navi = "% docker\n\n# Check running\ndocker inspect -f '{{.State.Running}}{{end}}' <container>\n";
+    let items = CheatFormat::Navi.parse(navi, "docker").unwrap();
+    assert_eq!(items[0].command, r"docker inspect -f '{{.State.Running}}\{{end}}' {{container}}");
+
+    let values = [("container".to_string(), "web".to_string())].into_iter().collect();
+    assert_eq!(
+        crate::template::render(&items[0].command, &values).unwrap(),
+        "docker inspect -f '{{.State.Running}}{{end}}' web"
+    );
+}
+
+#[test]
+fn test_parse_pet() {
+    let items = CheatFormat::Pet.parse(PET, "snippet").unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].name, "ping-google");
+    assert_eq!(items[0].command, "ping -c {{count=3}} 8.8.8.8 > /dev/null");
+    assert_eq!(items[0].tags, vec!["network"]);
+    assert!(CheatFormat::Pet.parse("snippets = 1", "snippet").is_err());
+}
+
+#[test]
+fn test_parse_tldr() {
+    let items = CheatFormat::Tldr.parse(TLDR, "tar").unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].name, "tar-create-an-archive-from-files");
+    assert_eq!(items[0].description, "create an archive from files");
+    assert_eq!(items[0].command, "tar cf {{path_to_target_tar}} {{path_to_file1_path_to_file2}}");
+    assert_eq!(items[1].tags, vec!["tar", "tldr"]);
+}
+
+#[test]
+fn test_tldr_page_tags_are_normalized() {
+    let temp_dir = TempDir::new().unwrap();
+    let bookmark = Bookmark::with_path(temp_dir.path().join("bookmarks.json"));
+
+    let page = "# git commit\n\n- Commit staged files:\n\n`git commit --message {{message}}`\n";
+    let items = CheatFormat::Tldr.parse(page, "git-commit").unwrap();
+    assert_eq!(items[0].tags, vec!["git-commit", "tldr"]);
+
+    bookmark.import(items, ConflictPolicy::Skip).unwrap();
+    assert_eq!(bookmark.list(&TagFilter::all(["git-commit"])).unwrap().len(), 1);
+}
+
+#[test]
+fn test_import_conflicts() {
+    let temp_dir = TempDir::new().unwrap();
+    let bookmark = Bookmark::with_path(temp_dir.path().join("bookmarks.json"));
+    bookmark.add(BookmarkItem {
+        name: "change-branch".to_string(),
+        command: "git switch -".to_string(),
+        ..Default::default()
+    }).unwrap();
+
+    let items = CheatFormat::Navi.parse(NAVI, "git").unwrap();
+    let summary = bookmark.import(items.clone(), ConflictPolicy::Skip).unwrap();
+    assert_eq!(summary, ImportSummary { added: 1, skipped: 1, ..Default::default() });
+
+    // Already imported commands are never duplicated
+    let summary = bookmark.import(items.clone(), ConflictPolicy::Rename).unwrap();
+    assert_eq!(summary, ImportSummary { renamed: 1, skipped: 1, ..Default::default() });
+    assert_eq!(bookmark.get("change-branch-2").unwrap().unwrap().command, "git checkout {{branch}}");
+
+    let mut replacement = items[1].clone();
+    replacement.command = "git show HEAD".to_string();
+    let summary = bookmark.import(vec![replacement], ConflictPolicy::Overwrite).unwrap();
+    assert_eq!(summary.replaced, 1);
+    assert_eq!(bookmark.get("show-file-at-revision").unwrap().unwrap().command, "git show HEAD");
+}
@@ -0,0 +1,127 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::bookmark::{BookmarkData, BookmarkItem};
+
+/// A read-only bookmark collection shared through a file or a directory of
+/// files, such as `.shai/bookmarks.toml` checked into a repository.
+///
+/// Collection files are TOML with a `[[bookmarks]]` table per bookmark, or
+/// JSON in the same shape as the personal bookmarks file. Bookmarks are
+/// listed as `namespace/name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookmarkSource {
+    pub namespace: String,
+    pub path: PathBuf,
+}
+
+impl BookmarkSource {
+    pub fn new(namespace: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self { namespace: namespace.into(), path: path.into() }
+    }
+
+    /// Uses the file or directory name as namespace. A file named
+    /// `bookmarks.*` is named after its project directory instead.
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let namespace = default_namespace(&path);
+        Self { namespace, path }
+    }
+
+    /// Reads the collection's bookmarks, prefixed with the namespace and
+    /// tagged with the file they came from.
+    pub fn load(&self) -> Result<Vec<BookmarkItem>, Box<dyn std::error::Error>> {
+        let files = if self.path.is_dir() {
+            let mut files: Vec<PathBuf> = fs::read_dir(&self.path)
+                .map_err(|e| format!("Cannot read {}: {}", self.path.display(), e))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| matches!(extension(path).as_deref(), Some("toml" | "json")))
+                .collect();
+            files.sort();
+            files
+        } else {
+            vec![self.path.clone()]
+        };
+
+        let mut items = Vec::new();
+        for file in files {
+            let content = fs::read_to_string(&file)
+                .map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+            let data: BookmarkData = match extension(&file).as_deref() {
+                Some("json") => serde_json::from_str(&content)
+                    .map_err(|e| format!("Invalid bookmarks in {}: {}", file.display(), e))?,
+                _ => toml::from_str(&content)
+                    .map_err(|e| format!("Invalid bookmarks in {}: {}", file.display(), e))?,
+            };
+
+            for mut item in data.bookmarks {
+                item.placeholders()
+                    .map_err(|e| format!("Invalid bookmark '{}' in {}: {}", item.name, file.display(), e))?;
+                item.name = format!("{}/{}", self.namespace, item.name);
+                item.source = Some(file.display().to_string());
+                items.push(item);
+            }
+        }
+
+        Ok(items)
+    }
+}
+
+/// Sources listed in `SHAI_BOOKMARK_SOURCES` (paths separated like `PATH`,
+/// each optionally written `namespace=path`), followed by the nearest
+/// `.shai/bookmarks.toml` in the current directory or its parents. Listed
+/// paths that don't exist are skipped with a warning.
+pub fn sources_from_env() -> Result<Vec<BookmarkSource>, Box<dyn std::error::Error>> {
+    let mut sources = Vec::new();
+
+    if let Some(value) = std::env::var_os("SHAI_BOOKMARK_SOURCES") {
+        for entry in std::env::split_paths(&value) {
+            let entry_str = entry.to_string_lossy();
+            if entry_str.trim().is_empty() {
+                continue;
+            }
+            let source = match entry_str.split_once('=') {
+                Some((namespace, path)) => BookmarkSource::new(namespace.trim(), path.trim()),
+                None => BookmarkSource::from_path(entry.clone()),
+            };
+            if !source.path.exists() {
+                eprintln!("Warning: skipping bookmark source {}: it does not exist", source.path.display());
+                continue;
+            }
+            sources.push(source);
+        }
+    }
+
+    if let Some(found) = std::env::current_dir().ok().and_then(|dir| find_project_collection(&dir)) {
+        if !sources.iter().any(|s| s.path == found) {
+            sources.push(BookmarkSource::from_path(found));
+        }
+    }
+
+    Ok(sources)
+}
+
+/// The nearest `.shai/bookmarks.toml` in `dir` or one of its parents.
+pub fn find_project_collection(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|ancestor| ancestor.join(".shai").join("bookmarks.toml"))
+        .find(|path| path.is_file())
+}
+
+fn default_namespace(path: &Path) -> String {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    if path.is_dir() || stem != "bookmarks" {
+        return stem;
+    }
+
+    // `<project>/.shai/bookmarks.toml` is named after `<project>`
+    path.ancestors()
+        .skip(1)
+        .filter_map(|dir| dir.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .find(|name| name != ".shai")
+        .unwrap_or(stem)
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase())
+}
@@ -0,0 +1,105 @@
+use std::fs;
+use crate::collection::{find_project_collection, BookmarkSource};
+use crate::{Bookmark, BookmarkItem, TagFilter};
+use tempfile::TempDir;
+
+const TEAM_TOML: &str = r#"
+[[bookmarks]]
+name = "deploy"
+command = "make deploy ENV={{env:staging|prod=staging}}"
+description = "Deploy the service"
+tags = ["ops"]
+
+[[bookmarks]]
+name = "logs"
+command = "kubectl logs -f deploy/api"
+"#;
+
+fn setup_with_collection() -> (Bookmark, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let shai_dir = temp_dir.path().join("api").join(".shai");
+    fs::create_dir_all(&shai_dir).unwrap();
+    fs::write(shai_dir.join("bookmarks.toml"), TEAM_TOML).unwrap();
+
+    let bookmark = Bookmark::with_path(temp_dir.path().join("bookmarks.json"))
+        .with_sources(vec![BookmarkSource::from_path(shai_dir.join("bookmarks.toml"))]);
+    (bookmark, temp_dir)
+}
+
+#[test]
+fn test_shared_bookmarks_are_merged_with_namespace() {
+    let (bookmark, _temp_dir) = setup_with_collection();
+    bookmark.add(BookmarkItem {
+        name: "list".to_string(),
+        command: "ls".to_string(),
+        ..Default::default()
+    }).unwrap();
+
+    let names: Vec<String> = bookmark.list(&TagFilter::default()).unwrap().into_iter().map(|b| b.name).collect();
+    assert_eq!(names, vec!["list", "api/deploy", "api/logs"]);
+
+    let deploy = bookmark.get("api/deploy").unwrap().unwrap();
+    assert!(deploy.source.unwrap().ends_with("bookmarks.toml"));
+    assert_eq!(bookmark.list(&TagFilter::all(["ops"])).unwrap().len(), 1);
+    assert_eq!(bookmark.search("kubectl").unwrap()[0].name, "api/logs");
+}
+
+#[test]
+fn test_shared_bookmarks_are_read_only() {
+    let (bookmark, _temp_dir) = setup_with_collection();
+
+    assert!(bookmark.remove("api/logs").is_err());
+    assert!(bookmark.update("api/logs", |b| b.command = "ls".to_string()).is_err());
+    bookmark.record_use("api/logs").unwrap();
+    assert_eq!(bookmark.get("api/logs").unwrap().unwrap().use_count, 0);
+}
+
+#[test]
+fn test_unique_name_avoids_shared_names() {
+    let (bookmark, _temp_dir) = setup_with_collection();
+    assert_eq!(bookmark.unique_name("api/deploy").unwrap(), "api/deploy-2");
+    assert_eq!(bookmark.unique_name("deploy").unwrap(), "deploy");
+}
+
+#[test]
+fn test_directory_source_and_discovery() {
+    let temp_dir = TempDir::new().unwrap();
+    let runbooks = temp_dir.path().join("runbooks");
+    fs::create_dir_all(&runbooks).unwrap();
+    fs::write(runbooks.join("db.toml"), "[[bookmarks]]\nname = \"backup\"\ncommand = \"pg_dump app\"\n").unwrap();
+    fs::write(runbooks.join("notes.txt"), "ignored").unwrap();
+
+    let items = BookmarkSource::from_path(&runbooks).load().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].name, "runbooks/backup");
+
+    fs::write(runbooks.join("broken.toml"), "[[bookmarks]]\nname = ").unwrap();
+    assert!(BookmarkSource::from_path(&runbooks).load().is_err());
+
+    let project = temp_dir.path().join("project");
+    fs::create_dir_all(project.join(".shai")).unwrap();
+    fs::create_dir_all(project.join("src").join("deep")).unwrap();
+    fs::write(project.join(".shai").join("bookmarks.toml"), TEAM_TOML).unwrap();
+    let found = find_project_collection(&project.join("src").join("deep")).unwrap();
+    assert_eq!(BookmarkSource::from_path(found).namespace, "project");
+}
+
+#[test]
+fn test_broken_source_is_skipped() {
+    let (bookmark, temp_dir) = setup_with_collection();
+    let broken = temp_dir.path().join("broken.toml");
+    fs::write(&broken, "[[bookmarks]]\nname = \"bad\"\ncommand = \"deploy {{env:dev|prod=test}}\"\n").unwrap();
+    let bookmark = bookmark.with_sources(vec![
+        BookmarkSource::from_path(&broken),
+        BookmarkSource::from_path(temp_dir.path().join("api").join(".shai").join("bookmarks.toml")),
+    ]);
+    bookmark.add(BookmarkItem {
+        name: "mine".to_string(),
+        command: "ls".to_string(),
+        ..Default::default()
+    }).unwrap();
+
+    let names: Vec<String> = bookmark.list(&TagFilter::default()).unwrap().into_iter().map(|b| b.name).collect();
+    assert_eq!(names, vec!["mine", "api/deploy", "api/logs"]);
+    assert!(bookmark.get("mine").unwrap().is_some());
+}
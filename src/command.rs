@@ -1,54 +1,256 @@
 use std::fmt::Display;
-use cmd_lib::run_cmd;
 
 #[derive(Debug, Clone, Default)]
 pub struct Suggestion {
     pub command: String,
+    /// Where the suggestion came from when not the model, e.g. "history"
+    pub origin: Option<String>,
 }
 
 impl Suggestion {
     pub fn new(command: String) -> Self {
-        Self { command }
+        Self { command, origin: None }
     }
 
-    pub fn execute(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let command = self.command.as_str();
-        if cfg!(windows) {
-            run_cmd!(cmd.exe /C "$command")?;
+    pub fn with_origin(mut self, origin: &str) -> Self {
+        self.origin = Some(origin.to_string());
+        self
+    }
+
+    /// Runs the command in the platform shell and returns its exit code.
+    pub fn run(&self) -> Result<i32, Box<dyn std::error::Error>> {
+        let status = if cfg!(windows) {
+            std::process::Command::new("cmd.exe").args(["/C", &self.command]).status()?
         } else {
-            run_cmd!(bash -c "$command")?;
+            std::process::Command::new("bash").args(["-c", &self.command]).status()?
+        };
+        // A missing code means the process was killed by a signal
+        Ok(status.code().unwrap_or(-1))
+    }
+}
+
+/// Runs `script` from a temporary file with `interpreter`, a program with
+/// optional arguments such as `python3 -u`, and returns its exit code.
+pub fn run_script(script: &str, interpreter: &str) -> Result<i32, Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    let mut parts = interpreter.split_whitespace();
+    let program = parts.next().ok_or("Interpreter is empty")?;
+    let args: Vec<&str> = parts.collect();
+
+    // Some interpreters only accept scripts with their own extension
+    let extension = match program.rsplit(['/', '\\']).next().unwrap_or(program) {
+        "pwsh" | "powershell" | "pwsh.exe" | "powershell.exe" => ".ps1",
+        "cmd" | "cmd.exe" => ".bat",
+        _ => "",
+    };
+    // A fresh file only the user can read, removed when `file` is dropped
+    let mut file = tempfile::Builder::new().prefix("shai-script-").suffix(extension).tempfile()?;
+    file.write_all(script.as_bytes())?;
+    if !script.ends_with('\n') {
+        file.write_all(b"\n")?;
+    }
+    file.flush()?;
+
+    let status = std::process::Command::new(program).args(&args).arg(file.path()).status()
+        .map_err(|e| format!("Cannot start interpreter '{}': {}", interpreter, e))?;
+    Ok(status.code().unwrap_or(-1))
+}
+
+/// Copies `text` to the system clipboard using the platform's clipboard tool.
+pub fn copy_to_clipboard(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+    use std::process::{Command as Process, Stdio};
+
+    let tools: &[(&str, &[&str])] = if cfg!(windows) {
+        &[("clip", &[])]
+    } else if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else {
+        &[("wl-copy", &[]), ("xclip", &["-selection", "clipboard"]), ("xsel", &["--clipboard", "--input"])]
+    };
+
+    for (tool, args) in tools {
+        let child = Process::new(tool).args(*args).stdin(Stdio::piped()).spawn();
+        if let Ok(mut child) = child {
+            if let Some(stdin) = child.stdin.as_mut() {
+                stdin.write_all(text.as_bytes())?;
+            }
+            if child.wait()?.success() {
+                return Ok(());
+            }
         }
-        Ok(())
     }
+
+    Err("No clipboard tool found (tried pbcopy, wl-copy, xclip, xsel, clip)".into())
 }
 
 impl Display for Suggestion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.command)
+        match &self.origin {
+            Some(origin) => write!(f, "{}  ({})", self.command, origin),
+            None => write!(f, "{}", self.command),
+        }
     }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Command {
+    /// Stable id assigned by `History`; 0 until the entry is stored
+    #[serde(default)]
+    pub id: u64,
     pub description: String,
     pub command: String,
     pub timestamp: i64,
     pub executed: bool,
+    /// Working directory the command was run from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    /// Model that produced the suggestion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// API endpoint the model was served from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// All suggestions offered for the description
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+    /// Whether the user modified the selected suggestion before running it
+    #[serde(default)]
+    pub edited: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Exit code of the executed command, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Number of times this command was recorded
+    #[serde(default = "default_count")]
+    pub count: u32,
+    /// When the command was last recorded, if more than once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<i64>,
+    /// Starred entries are kept by the retention policy
+    #[serde(default)]
+    pub starred: bool,
+    /// Where the entry came from, e.g. `zsh` for imported shell history; `None` for shai itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Name of the bookmark the command was run from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bookmark: Option<String>,
 }
 
 impl Command {
     pub fn new(description: String, command: String) -> Self {
         Self {
+            id: 0,
             description,
             command,
             timestamp: chrono::Utc::now().timestamp(),
             executed: false,
+            cwd: None,
+            hostname: None,
+            shell: None,
+            model: None,
+            provider: None,
+            suggestions: Vec::new(),
+            edited: false,
+            session_id: None,
+            exit_code: None,
+            count: 1,
+            last_used: None,
+            starred: false,
+            source: None,
+            bookmark: None,
         }
     }
 
+    /// When the command was last recorded.
+    pub fn last_used(&self) -> i64 {
+        self.last_used.unwrap_or(self.timestamp)
+    }
+
+    /// Command text with whitespace collapsed, used to detect duplicates.
+    pub fn normalized_command(&self) -> String {
+        self.command.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
     pub fn with_executed(mut self, executed: bool) -> Self {
         self.executed = executed;
         self
     }
+
+    /// Records the current working directory, hostname, shell and session.
+    pub fn with_environment(mut self) -> Self {
+        self.cwd = current_dir_string();
+        self.hostname = hostname();
+        self.shell = shell_name();
+        self.session_id = session_id();
+        self
+    }
+
+    pub fn with_model(mut self, model: &str, provider: &str) -> Self {
+        self.model = Some(model.to_string());
+        self.provider = Some(provider.to_string());
+        self
+    }
+
+    pub fn with_suggestions(mut self, suggestions: Vec<String>) -> Self {
+        self.suggestions = suggestions;
+        self
+    }
+
+    pub fn with_edited(mut self, edited: bool) -> Self {
+        self.edited = edited;
+        self
+    }
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+pub fn current_dir_string() -> Option<String> {
+    std::env::current_dir()
+        .ok()
+        .map(|dir| dir.to_string_lossy().into_owned())
 }
 
+fn hostname() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+fn shell_name() -> Option<String> {
+    std::env::var("SHELL")
+        .or_else(|_| std::env::var("COMSPEC"))
+        .ok()
+        .and_then(|path| {
+            std::path::Path::new(&path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+}
+
+/// Uses `SHAI_SESSION_ID` when set, otherwise the parent shell's process id.
+fn session_id() -> Option<String> {
+    if let Ok(id) = std::env::var("SHAI_SESSION_ID") {
+        return Some(id);
+    }
+    #[cfg(unix)]
+    {
+        Some(std::os::unix::process::parent_id().to_string())
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
@@ -1,5 +1,45 @@
 use std::env;
 use std::env::current_dir;
+use std::str::FromStr;
+use crate::redact::Redactor;
+use crate::storage::{get_config_dir, home_dir};
+
+/// Loads `.env` files into the process environment so settings apply to every
+/// subcommand, not only those that build a `Config`.
+pub fn load_env_files() {
+    // First load .env from the XDG config directory (global config)
+    if let Ok(config_dir) = get_config_dir() {
+        let _ = dotenvy::from_path(config_dir.join(".env"));
+    }
+    // Then fall back to .env in the home directory (legacy global config)
+    if let Some(home) = home_dir() {
+        let _ = dotenvy::from_path(home.join(".env"));
+    }
+    // Then load .env from current directory (can override global config)
+    if let Ok(current_dir) = current_dir() {
+        let _ = dotenvy::from_path_override(current_dir.join(".env"));
+    }
+}
+
+/// Returns the trimmed value of `key`, or `None` when it is unset or blank.
+pub fn env_value(key: &str) -> Option<String> {
+    env::var(key).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// Reads a boolean setting: `0`, `false`, `no` and `off` turn it off, any
+/// other value turns it on, and `default` applies when it is unset.
+pub fn env_bool(key: &str, default: bool) -> bool {
+    env_value(key)
+        .map(|v| !matches!(v.to_lowercase().as_str(), "0" | "false" | "no" | "off"))
+        .unwrap_or(default)
+}
+
+/// Reads a numeric setting, failing on a value that doesn't parse.
+pub fn env_number<T: FromStr>(key: &str) -> Result<Option<T>, Box<dyn std::error::Error>> {
+    env_value(key)
+        .map(|value| value.parse().map_err(|_| format!("Invalid {} '{}'", key, value).into()))
+        .transpose()
+}
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -7,36 +47,47 @@ pub struct Config {
     pub endpoint: String,
     pub model: String,
     pub suggestion_count: i32,
+    pub redactor: Redactor,
+    /// Number of similar past commands sent to the model as examples
+    pub history_examples: usize,
+    /// Whether similar past commands are offered before the model's suggestions
+    pub history_suggestions: bool,
+    /// Whether model responses are cached locally
+    pub cache: bool,
+    /// Whether bookmarks similar in meaning are offered before the model's suggestions
+    pub bookmark_suggestions: bool,
+    /// Provider model for bookmark embeddings; local hashing is used when unset
+    pub embedding_model: Option<String>,
 }
 
 impl Config {
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
-        // First load .env from home directory (global config)
-        if let Some(home) = env::var_os("HOME") {
-            let env_path = std::path::PathBuf::from(home).join(".env");
-            let _ = dotenvy::from_path(&env_path);
-        }
-        // Then load .env from current directory (can override global config)
-        if let Ok(current_dir) = current_dir() {
-            let _ = dotenvy::from_path_override(current_dir.join(".env"));
-        }
-        
+        load_env_files();
+
         let api_key = env::var("SHAI_API_KEY")
             .expect("SHAI_API_KEY must be set");
         let endpoint = env::var("SHAI_API_ENDPOINT")
             .unwrap_or_else(|_| "https://openrouter.ai/api/v1".to_string());
         let model = env::var("SHAI_MODEL")
             .unwrap_or_else(|_| "meta-llama/llama-3.1-70b-instruct:free".to_string());
-        let suggestion_count: i32 = env::var("SHAI_SUGGESTION_COUNT")
-            .unwrap_or_else(|_| "2".to_string())
-            .parse()
-            .unwrap_or(2);
+        let suggestion_count: i32 = env_number("SHAI_SUGGESTION_COUNT").ok().flatten().unwrap_or(2);
+        let history_examples: usize = env_number("SHAI_HISTORY_EXAMPLES").ok().flatten().unwrap_or(3);
+        let history_suggestions = env_bool("SHAI_HISTORY_SUGGESTIONS", true);
+        let cache = env_bool("SHAI_CACHE", true);
+        let bookmark_suggestions = env_bool("SHAI_BOOKMARK_SUGGESTIONS", true);
+        let embedding_model = env_value("SHAI_EMBEDDING_MODEL");
 
         Ok(Self {
             api_key,
             endpoint,
             model,
             suggestion_count,
+            redactor: Redactor::from_env()?,
+            history_examples,
+            history_suggestions,
+            cache,
+            bookmark_suggestions,
+            embedding_model,
         })
     }
 }
@@ -0,0 +1,382 @@
+use crate::bookmark::BookmarkItem;
+use crate::command::Command;
+use crate::shell_history::Shell;
+use crate::template::{self, Placeholder, QuoteContext};
+
+/// Output formats for `shai history export` and `shai bookmark export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON object per line
+    Jsonl,
+    Csv,
+    /// Markdown table
+    Markdown,
+    /// Shell script; bookmarks become commented functions
+    Shell,
+}
+
+pub fn export_history(commands: &[Command], format: ExportFormat) -> Result<String, Box<dyn std::error::Error>> {
+    let mut out = String::new();
+
+    match format {
+        ExportFormat::Jsonl => {
+            for cmd in commands {
+                out.push_str(&serde_json::to_string(cmd)?);
+                out.push('\n');
+            }
+        }
+        ExportFormat::Csv => {
+            out.push_str("time,description,command,executed,cwd,model,source\n");
+            for cmd in commands {
+                let row = [
+                    format_time(cmd.timestamp),
+                    cmd.description.clone(),
+                    cmd.command.clone(),
+                    cmd.executed.to_string(),
+                    cmd.cwd.clone().unwrap_or_default(),
+                    cmd.model.clone().unwrap_or_default(),
+                    cmd.source.clone().unwrap_or_default(),
+                ];
+                out.push_str(&csv_row(&row));
+            }
+        }
+        ExportFormat::Markdown => {
+            out.push_str("| Time | Description | Command |\n");
+            out.push_str("|------|-------------|---------|\n");
+            for cmd in commands {
+                out.push_str(&format!(
+                    "| {} | {} | {} |\n",
+                    format_time(cmd.timestamp),
+                    markdown_cell(&cmd.description),
+                    markdown_cell(&code_span(&cmd.command)),
+                ));
+            }
+        }
+        ExportFormat::Shell => {
+            out.push_str("#!/usr/bin/env bash\n# Exported from shai history\n");
+            for cmd in commands {
+                out.push_str(&format!("\n# {}", format_time(cmd.timestamp)));
+                if !cmd.description.is_empty() {
+                    out.push_str(&format!(": {}", single_line(&cmd.description)));
+                }
+                out.push('\n');
+                out.push_str(&cmd.command);
+                out.push('\n');
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+pub fn export_bookmarks(items: &[BookmarkItem], format: ExportFormat) -> Result<String, Box<dyn std::error::Error>> {
+    let mut out = String::new();
+
+    match format {
+        ExportFormat::Jsonl => {
+            for item in items {
+                out.push_str(&serde_json::to_string(item)?);
+                out.push('\n');
+            }
+        }
+        ExportFormat::Csv => {
+            out.push_str("name,command,description,tags,created\n");
+            for item in items {
+                let row = [
+                    item.name.clone(),
+                    item.command.clone(),
+                    item.description.clone(),
+                    item.tags.join(";"),
+                    format_time(item.created_at),
+                ];
+                out.push_str(&csv_row(&row));
+            }
+        }
+        ExportFormat::Markdown => {
+            out.push_str("| Name | Command | Description | Tags |\n");
+            out.push_str("|------|---------|-------------|------|\n");
+            for item in items {
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    markdown_cell(&item.name),
+                    markdown_cell(&code_span(&item.command)),
+                    markdown_cell(&item.description),
+                    markdown_cell(&item.tags.join(", ")),
+                ));
+            }
+        }
+        ExportFormat::Shell => {
+            out.push_str("#!/usr/bin/env bash\n# Exported from shai bookmarks\n");
+            for item in items {
+                out.push('\n');
+                if !item.description.is_empty() {
+                    out.push_str(&format!("# {}\n", single_line(&item.description)));
+                }
+                if !item.tags.is_empty() {
+                    out.push_str(&format!("# tags: {}\n", item.tags.join(", ")));
+                }
+                let name = function_name(&item.name);
+                let placeholders = match item.placeholders() {
+                    Ok(placeholders) => placeholders,
+                    Err(e) => {
+                        out.push_str(&format!("# Skipped {}: {}\n", single_line(&item.name), single_line(&e.to_string())));
+                        continue;
+                    }
+                };
+                match &item.interpreter {
+                    // The script's own arguments can't also carry placeholder values
+                    Some(_) if !placeholders.is_empty() => out.push_str(&format!(
+                        "# Skipped {}: its placeholders are only filled in by `shai bookmark run`\n",
+                        single_line(&item.name)
+                    )),
+                    // A quoted here-document keeps the script verbatim
+                    Some(interpreter) => out.push_str(&format!(
+                        "{}() {{\n    {} <(cat <<'SHAI_SCRIPT'\n{}\nSHAI_SCRIPT\n) \"$@\"\n}}\n",
+                        name, interpreter, template::render_raw(&item.command, &Default::default())?.trim_end_matches('\n')
+                    )),
+                    None => out.push_str(&bash_function(&name, &item.command, &placeholders)?),
+                }
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Shell definitions for `items`, suitable for `eval "$(shai bookmark alias)"`.
+///
+/// Plain bookmarks become aliases. Bookmarks with placeholders become
+/// functions taking one positional argument per placeholder, in order of
+/// first use, with defaults and choices checked by the function. Script
+/// bookmarks become functions that call `shai bookmark run`.
+pub fn export_aliases(items: &[BookmarkItem], shell: Shell) -> Result<String, Box<dyn std::error::Error>> {
+    let mut out = format!("# Generated by `shai bookmark alias --shell {}`\n", shell.name());
+
+    for item in items {
+        let name = function_name(&item.name);
+        out.push('\n');
+        // One broken bookmark shouldn't cost the shell all the others
+        let placeholders = match item.placeholders() {
+            Ok(placeholders) => placeholders,
+            Err(e) => {
+                out.push_str(&format!("# Skipped {}: {}\n", single_line(&item.name), single_line(&e.to_string())));
+                continue;
+            }
+        };
+
+        // Scripts need their interpreter, so they are run through shai, taking
+        // `-a key=value` arguments
+        if item.is_script() {
+            let run = format!("shai bookmark run {} --yes", template::shell_quote(&item.name));
+            match shell {
+                Shell::Fish => {
+                    out.push_str(&format!("function {}", name));
+                    if !item.description.is_empty() {
+                        out.push_str(&format!(" --description {}", template::shell_quote(&single_line(&item.description))));
+                    }
+                    out.push_str(&format!("\n    {} $argv\nend\n", run));
+                }
+                Shell::Bash | Shell::Zsh => {
+                    if !item.description.is_empty() {
+                        out.push_str(&format!("# {}\n", single_line(&item.description)));
+                    }
+                    out.push_str(&format!("{}() {{\n    {} \"$@\"\n}}\n", name, run));
+                }
+            }
+            continue;
+        }
+
+        if placeholders.is_empty() && !item.command.contains('\n') {
+            // Rendering without values only unescapes `\{{`
+            let command = template::render(&item.command, &Default::default())?;
+            if shell != Shell::Fish && !item.description.is_empty() {
+                out.push_str(&format!("# {}\n", single_line(&item.description)));
+            }
+            match shell {
+                // What fish's own `alias` expands to, minus the version differences
+                Shell::Fish => {
+                    out.push_str(&format!("function {}", name));
+                    if !item.description.is_empty() {
+                        out.push_str(&format!(" --description {}", template::shell_quote(&single_line(&item.description))));
+                    }
+                    out.push_str(&format!("\n    {} $argv\nend\n", command));
+                }
+                Shell::Bash | Shell::Zsh => {
+                    out.push_str(&format!("alias {}={}\n", name, template::shell_quote(&command)))
+                }
+            }
+            continue;
+        }
+
+        match shell {
+            Shell::Bash | Shell::Zsh => {
+                if !item.description.is_empty() {
+                    out.push_str(&format!("# {}\n", single_line(&item.description)));
+                }
+                out.push_str(&bash_function(&name, &item.command, &placeholders)?);
+            }
+            Shell::Fish => {
+                out.push_str(&format!("function {}", name));
+                if !item.description.is_empty() {
+                    out.push_str(&format!(" --description {}", template::shell_quote(&single_line(&item.description))));
+                }
+                out.push('\n');
+                if let Some(required) = required_args(&placeholders) {
+                    out.push_str(&format!(
+                        "    if not set -q argv[{}]\n        echo {} >&2\n        return 1\n    end\n",
+                        required, usage(&name, &placeholders)
+                    ));
+                }
+                for (i, placeholder) in placeholders.iter().enumerate() {
+                    let variable = variable_name(placeholder);
+                    match &placeholder.default {
+                        Some(default) => out.push_str(&format!(
+                            "    set -l {} {}\n    set -q argv[{}]; and set {} $argv[{}]\n",
+                            variable, template::shell_quote(default), i + 1, variable, i + 1
+                        )),
+                        None => out.push_str(&format!("    set -l {} $argv[{}]\n", variable, i + 1)),
+                    }
+                    if !placeholder.choices.is_empty() {
+                        out.push_str(&format!(
+                            "    if not contains -- ${} {}\n        echo {} >&2\n        return 1\n    end\n",
+                            variable,
+                            placeholder.choices.iter().map(|c| template::shell_quote(c)).collect::<Vec<_>>().join(" "),
+                            template::shell_quote(&choices_error(placeholder)),
+                        ));
+                    }
+                }
+                out.push_str(&indent(&function_body(&item.command)?));
+                out.push_str("end\n");
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// A bash function taking one positional argument per placeholder, in order
+/// of first use, with defaults and choices checked by the function.
+fn bash_function(name: &str, command: &str, placeholders: &[Placeholder]) -> Result<String, Box<dyn std::error::Error>> {
+    let mut out = format!("{}() {{\n", name);
+    if let Some(required) = required_args(placeholders) {
+        out.push_str(&format!(
+            "    if [ $# -lt {} ]; then\n        echo {} >&2\n        return 1\n    fi\n",
+            required, usage(name, placeholders)
+        ));
+    }
+    for (i, placeholder) in placeholders.iter().enumerate() {
+        // The default sits inside double quotes, where `}` would also end it
+        let value = match &placeholder.default {
+            Some(default) => format!("${{{}:-{}}}", i + 1, template::double_quote_escape(default).replace('}', "\\}")),
+            None => format!("${}", i + 1),
+        };
+        out.push_str(&format!("    local {}=\"{}\"\n", variable_name(placeholder), value));
+        if !placeholder.choices.is_empty() {
+            out.push_str(&format!(
+                "    case \"${}\" in\n        {}) ;;\n        *) echo {} >&2; return 1 ;;\n    esac\n",
+                variable_name(placeholder),
+                placeholder.choices.iter().map(|c| template::shell_quote(c)).collect::<Vec<_>>().join("|"),
+                template::shell_quote(&choices_error(placeholder)),
+            ));
+        }
+    }
+    out.push_str(&indent(&function_body(command)?));
+    out.push_str("}\n");
+    Ok(out)
+}
+
+/// `command` with each placeholder replaced by its `arg_`-prefixed variable,
+/// so names like `path` cannot clobber special shell variables.
+fn function_body(command: &str) -> Result<String, Box<dyn std::error::Error>> {
+    template::substitute(command, |placeholder, context| {
+        let variable = format!("${}", variable_name(placeholder));
+        Ok(match context {
+            QuoteContext::Bare => format!("\"{}\"", variable),
+            QuoteContext::Double => variable,
+            QuoteContext::Single => format!("'\"{}\"'", variable),
+        })
+    })
+}
+
+/// The quoted usage message of a function taking `placeholders`.
+fn usage(name: &str, placeholders: &[Placeholder]) -> String {
+    let args = placeholders
+        .iter()
+        .map(|p| if p.default.is_some() { format!("[{}]", p.name) } else { format!("<{}>", p.name) })
+        .collect::<Vec<_>>()
+        .join(" ");
+    template::shell_quote(&format!("usage: {} {}", name, args))
+}
+
+/// The number of arguments up to the last placeholder without a default.
+fn required_args(placeholders: &[Placeholder]) -> Option<usize> {
+    placeholders.iter().rposition(|p| p.default.is_none()).map(|i| i + 1)
+}
+
+fn variable_name(placeholder: &Placeholder) -> String {
+    format!("arg_{}", placeholder.name.replace('-', "_"))
+}
+
+fn choices_error(placeholder: &Placeholder) -> String {
+    format!("{}: expected one of {}", placeholder.name, placeholder.choices.join(", "))
+}
+
+/// Indents a single-line command for a function body. Multi-line scripts are
+/// left as written so heredocs keep working.
+fn indent(body: &str) -> String {
+    if body.contains('\n') {
+        format!("{}\n", body.trim_end_matches('\n'))
+    } else {
+        format!("    {}\n", body)
+    }
+}
+
+/// Turns a bookmark name into a valid shell function name.
+pub fn function_name(name: &str) -> String {
+    let mut result: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+    result
+}
+
+fn format_time(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+fn csv_row(fields: &[String]) -> String {
+    let escaped: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect();
+    format!("{}\n", escaped.join(","))
+}
+
+/// Markdown inline code, fenced with more backticks than `value` contains in a row.
+fn code_span(value: &str) -> String {
+    let longest_run = value.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run + 1);
+    // A space keeps backticks at either end from merging into the fence
+    let padding = if value.starts_with('`') || value.ends_with('`') { " " } else { "" };
+    format!("{fence}{padding}{value}{padding}{fence}")
+}
+
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', "<br>")
+}
+
+fn single_line(value: &str) -> String {
+    value.replace('\n', " ")
+}
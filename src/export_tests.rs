@@ -0,0 +1,141 @@
+use crate::export::{export_aliases, export_bookmarks, export_history, function_name};
+use crate::{BookmarkItem, Command, ExportFormat, Shell};
+
+fn bookmark() -> BookmarkItem {
+    BookmarkItem {
+        name: "docker-clean".to_string(),
+        command: "docker system prune -af".to_string(),
+        description: "Remove unused data, images | volumes".to_string(),
+        tags: vec!["docker".to_string(), "cleanup".to_string()],
+        created_at: 0,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_export_history_csv_escaping() {
+    let command = Command::new("say \"hi\", loudly".to_string(), "echo hi".to_string());
+    let csv = export_history(&[command], ExportFormat::Csv).unwrap();
+
+    let row = csv.lines().nth(1).unwrap();
+    assert!(row.contains(",\"say \"\"hi\"\", loudly\",echo hi,false,"));
+}
+
+#[test]
+fn test_export_history_jsonl() {
+    let commands = vec![
+        Command::new("a".to_string(), "ls".to_string()),
+        Command::new("b".to_string(), "pwd".to_string()),
+    ];
+    let jsonl = export_history(&commands, ExportFormat::Jsonl).unwrap();
+
+    let parsed: Vec<Command> = jsonl.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(parsed[1].command, "pwd");
+}
+
+#[test]
+fn test_export_bookmarks_markdown() {
+    let markdown = export_bookmarks(&[bookmark()], ExportFormat::Markdown).unwrap();
+    assert!(markdown.contains("| docker-clean | `docker system prune -af` | Remove unused data, images \\| volumes | docker, cleanup |"));
+}
+
+#[test]
+fn test_export_markdown_backticks() {
+    let command = Command::new("date".to_string(), "echo `date`".to_string());
+    let markdown = export_history(&[command], ExportFormat::Markdown).unwrap();
+    assert!(markdown.contains("| date | `` echo `date` `` |"));
+
+    let mut item = bookmark();
+    item.command = "`pwd`".to_string();
+    let markdown = export_bookmarks(&[item], ExportFormat::Markdown).unwrap();
+    assert!(markdown.contains("| `` `pwd` `` |"));
+}
+
+#[test]
+fn test_export_bookmarks_shell() {
+    let script = export_bookmarks(&[bookmark()], ExportFormat::Shell).unwrap();
+    assert!(script.contains("# tags: docker, cleanup\ndocker_clean() {\n    docker system prune -af\n}\n"));
+}
+
+#[test]
+fn test_export_bookmarks_shell_placeholders() {
+    let mut item = bookmark();
+    item.name = "logs".to_string();
+    item.command = "docker logs --tail {{lines=100}} {{container}} | grep '\\{{'".to_string();
+
+    let script = export_bookmarks(std::slice::from_ref(&item), ExportFormat::Shell).unwrap();
+    assert!(script.contains("    local arg_lines=\"${1:-100}\"\n    local arg_container=\"$2\"\n"));
+    assert!(script.contains("    docker logs --tail \"$arg_lines\" \"$arg_container\" | grep '{{'\n}\n"));
+    assert!(!script.contains("{{lines"));
+
+    item.command = "echo {{env:dev|prod=test}}".to_string();
+    let script = export_bookmarks(&[item], ExportFormat::Shell).unwrap();
+    assert!(script.contains("# Skipped logs: "));
+    assert!(!script.contains("logs() {"));
+}
+
+#[test]
+fn test_function_name() {
+    assert_eq!(function_name("k8s logs"), "k8s_logs");
+    assert_eq!(function_name("2fa"), "_2fa");
+}
+
+#[test]
+fn test_export_aliases_plain() {
+    let bash = export_aliases(&[bookmark()], Shell::Bash).unwrap();
+    assert!(bash.contains("alias docker_clean='docker system prune -af'\n"));
+
+    let fish = export_aliases(&[bookmark()], Shell::Fish).unwrap();
+    assert!(fish.contains("function docker_clean --description 'Remove unused data, images | volumes'\n"));
+    assert!(fish.contains("    docker system prune -af $argv\nend\n"));
+}
+
+#[test]
+fn test_export_aliases_placeholders() {
+    let mut item = bookmark();
+    item.name = "logs".to_string();
+    item.command = "docker logs --tail {{lines=100}} {{container}}".to_string();
+
+    let bash = export_aliases(std::slice::from_ref(&item), Shell::Zsh).unwrap();
+    assert!(bash.contains("logs() {\n    if [ $# -lt 2 ]; then\n"));
+    assert!(bash.contains("    local arg_lines=\"${1:-100}\"\n    local arg_container=\"$2\"\n"));
+    assert!(bash.contains("    docker logs --tail \"$arg_lines\" \"$arg_container\"\n}\n"));
+
+    let fish = export_aliases(std::slice::from_ref(&item), Shell::Fish).unwrap();
+    assert!(fish.contains("    if not set -q argv[2]\n"));
+    assert!(fish.contains("    set -l arg_lines 100\n    set -q argv[1]; and set arg_lines $argv[1]\n"));
+    assert!(fish.contains("    set -l arg_container $argv[2]\n"));
+
+    item.command = "echo {{msg=hello \"big\" world}}".to_string();
+    let bash = export_aliases(&[item], Shell::Bash).unwrap();
+    assert!(bash.contains(r#"    local arg_msg="${1:-hello \"big\" world}""#));
+}
+
+#[test]
+fn test_export_aliases_skips_invalid_bookmarks() {
+    let mut broken = bookmark();
+    broken.name = "broken".to_string();
+    broken.command = "deploy {{env:dev|prod=test}}".to_string();
+
+    let bash = export_aliases(&[broken, bookmark()], Shell::Bash).unwrap();
+    assert!(bash.contains("# Skipped broken: Invalid value 'test' for 'env'"));
+    assert!(bash.contains("alias docker_clean="));
+}
+
+#[test]
+fn test_export_script_bookmarks() {
+    let item = BookmarkItem {
+        name: "greet".to_string(),
+        command: "import sys\nprint('hi', *sys.argv[1:])\n".to_string(),
+        interpreter: Some("python3".to_string()),
+        ..Default::default()
+    };
+
+    let script = export_bookmarks(std::slice::from_ref(&item), ExportFormat::Shell).unwrap();
+    assert!(script.contains("greet() {\n    python3 <(cat <<'SHAI_SCRIPT'\nimport sys\nprint('hi', *sys.argv[1:])\nSHAI_SCRIPT\n) \"$@\"\n}\n"));
+
+    let bash = export_aliases(std::slice::from_ref(&item), Shell::Bash).unwrap();
+    assert!(bash.contains("greet() {\n    shai bookmark run greet --yes \"$@\"\n}\n"));
+    let fish = export_aliases(&[item], Shell::Fish).unwrap();
+    assert!(fish.contains("function greet\n    shai bookmark run greet --yes $argv\nend\n"));
+}
@@ -1,45 +1,380 @@
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use crate::bookmark::{Bookmark, TagFilter};
 use crate::command::Command;
-use crate::storage::{Storage, get_data_dir};
+use crate::config::env_bool;
+use crate::query::Query;
+use crate::redact::Redactor;
+use crate::retention::RetentionPolicy;
+use crate::storage::{Storage, Encryption, get_data_dir};
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct HistoryData {
     pub commands: Vec<Command>,
+    /// Id handed to the next new entry
+    #[serde(default)]
+    pub next_id: u64,
 }
 
-impl Default for HistoryData {
-    fn default() -> Self {
-        Self {
-            commands: Vec::new(),
+impl HistoryData {
+    /// Gives every entry without an id (e.g. from older versions) a fresh one,
+    /// oldest first.
+    fn assign_ids(&mut self) {
+        let max_id = self.commands.iter().map(|c| c.id).max().unwrap_or(0);
+        self.next_id = self.next_id.max(max_id + 1);
+
+        let mut unassigned: Vec<&mut Command> = self.commands.iter_mut().filter(|c| c.id == 0).collect();
+        unassigned.sort_by_key(|c| c.timestamp);
+        for command in unassigned {
+            command.id = self.next_id;
+            self.next_id += 1;
+        }
+    }
+
+    fn get_mut(&mut self, id: u64) -> Result<&mut Command, Box<dyn std::error::Error>> {
+        self.commands
+            .iter_mut()
+            .find(|c| c.id == id)
+            .ok_or_else(|| format!("History entry {} not found", id).into())
+    }
+}
+
+/// Metadata filters for `History::filter`. Unset fields match everything.
+#[derive(Debug, Default, Clone)]
+pub struct HistoryFilter {
+    /// Only entries run from this working directory
+    pub cwd: Option<String>,
+    /// Only entries produced by this model
+    pub model: Option<String>,
+    /// Only entries whose suggestion was edited before running
+    pub edited: bool,
+    /// Only entries at or after this Unix timestamp
+    pub since: Option<i64>,
+    /// Only entries at or before this Unix timestamp
+    pub until: Option<i64>,
+    /// Only entries that were executed
+    pub executed: bool,
+    /// Only entries whose command exited with a non-zero status
+    pub failed: bool,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, command: &Command) -> bool {
+        if let Some(cwd) = &self.cwd {
+            if command.cwd.as_ref() != Some(cwd) {
+                return false;
+            }
+        }
+        if let Some(model) = &self.model {
+            if command.model.as_ref() != Some(model) {
+                return false;
+            }
+        }
+        if self.since.is_some_and(|since| command.timestamp < since) {
+            return false;
+        }
+        if self.until.is_some_and(|until| command.timestamp > until) {
+            return false;
+        }
+        if self.executed && !command.executed {
+            return false;
+        }
+        if self.failed && command.exit_code.is_none_or(|code| code == 0) {
+            return false;
+        }
+        !self.edited || command.edited
+    }
+}
+
+/// Parses a `YYYY-MM-DD` date (local time) or an RFC 3339 timestamp into a
+/// Unix timestamp. Dates resolve to the start of the day, or its end when
+/// `end_of_day` is set.
+pub fn parse_date(value: &str, end_of_day: bool) -> Result<i64, Box<dyn std::error::Error>> {
+    use chrono::{Local, NaiveDate, TimeZone};
+
+    if let Ok(datetime) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.timestamp());
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date '{}': expected YYYY-MM-DD or RFC 3339", value))?;
+    let time = if end_of_day {
+        date.and_hms_opt(23, 59, 59)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    };
+    let local = time
+        .and_then(|t| Local.from_local_datetime(&t).earliest())
+        .ok_or_else(|| format!("Invalid local date '{}'", value))?;
+    Ok(local.timestamp())
+}
+
+/// Ordering for `History::list_by` and `History::search_by`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum HistorySort {
+    /// Most recently used first
+    #[default]
+    Recent,
+    /// Frequently and recently used first
+    Frecency,
+}
+
+/// Frecency score: use count weighted by how recently the command was last used.
+pub fn frecency(command: &Command, now: i64) -> u64 {
+    const DAY: i64 = 24 * 60 * 60;
+    let age = now - command.last_used();
+    let weight = match age {
+        a if a <= 4 * DAY => 100,
+        a if a <= 14 * DAY => 70,
+        a if a <= 31 * DAY => 50,
+        a if a <= 90 * DAY => 30,
+        _ => 10,
+    };
+    command.count as u64 * weight
+}
+
+fn sort_commands(commands: &mut [Command], order: HistorySort) {
+    match order {
+        HistorySort::Recent => commands.sort_by_key(|c| std::cmp::Reverse(c.last_used())),
+        HistorySort::Frecency => {
+            let now = chrono::Utc::now().timestamp();
+            commands.sort_by_key(|c| std::cmp::Reverse((frecency(c, now), c.last_used())));
         }
     }
 }
 
-pub struct History;
+pub struct History {
+    path: PathBuf,
+    encryption: Option<Encryption>,
+    redactor: Redactor,
+    retention: RetentionPolicy,
+    bookmarks: Option<Bookmark>,
+    dedup: bool,
+}
 
 impl History {
-    pub fn new() -> Self {
-        Self
+    /// Opens the history file in the shai data directory, encrypted if
+    /// configured through the environment.
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut store = Self::with_path(get_data_dir()?.join("history.json"));
+        store.encryption = Encryption::from_env()?;
+        store.redactor = Redactor::from_env()?;
+        store.retention = RetentionPolicy::from_env()?;
+        store.bookmarks = Some(Bookmark::personal()?);
+        store.dedup = env_bool("SHAI_HISTORY_DEDUP", true);
+        Ok(store)
+    }
+
+    /// Opens the history file at `path`.
+    pub fn with_path(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            encryption: None,
+            redactor: Redactor::default(),
+            retention: RetentionPolicy::default(),
+            bookmarks: None,
+            dedup: true,
+        }
+    }
+
+    pub fn with_encryption(mut self, encryption: Encryption) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Whether re-recording a command bumps the existing entry instead of
+    /// adding a new one.
+    pub fn with_dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Bookmarks whose commands are protected from pruning.
+    pub fn with_bookmarks(mut self, bookmarks: Bookmark) -> Self {
+        self.bookmarks = Some(bookmarks);
+        self
     }
 
-    pub fn add(&self, command: Command) -> Result<(), Box<dyn std::error::Error>> {
+    /// Records a command and returns the id of its entry.
+    pub fn add(&self, command: Command) -> Result<u64, Box<dyn std::error::Error>> {
         let mut data = self.load_data()?;
-        data.commands.push(command);
-        
-        // Keep only last 1000 commands
-        if data.commands.len() > 1000 {
-            data.commands.drain(0..data.commands.len() - 1000);
+        let mut command = self.redact(command);
+
+        let id = match self.dedup_index(&data).get(&command.normalized_command()) {
+            Some(&i) => {
+                merge_into(&mut data.commands[i], command);
+                data.commands[i].id
+            }
+            None => {
+                command.id = data.next_id;
+                data.next_id += 1;
+                data.commands.push(command);
+                data.next_id - 1
+            }
+        };
+
+        self.apply_retention(&mut data)?;
+        self.save(&data)?;
+        Ok(id)
+    }
+
+    pub fn get(&self, id: u64) -> Result<Option<Command>, Box<dyn std::error::Error>> {
+        let data = self.load_data()?;
+        Ok(data.commands.into_iter().find(|c| c.id == id))
+    }
+
+    /// Applies `edit` to the entry with `id`. Edited text is redacted again.
+    /// When the edit makes the entry a duplicate of another, that entry is
+    /// folded into this one.
+    pub fn update(&self, id: u64, edit: impl FnOnce(&mut Command)) -> Result<(), Box<dyn std::error::Error>> {
+        let mut data = self.load_data()?;
+        let entry = data.get_mut(id)?;
+        edit(entry);
+        *entry = self.redact(entry.clone());
+
+        if self.dedup {
+            let normalized = entry.normalized_command();
+            if let Some(i) = data.commands.iter().position(|c| c.id != id && c.normalized_command() == normalized) {
+                let duplicate = data.commands.remove(i);
+                let entry = data.get_mut(id)?;
+                count_use(entry, &duplicate);
+                entry.starred |= duplicate.starred;
+            }
         }
-        
+
         self.save(&data)
     }
 
+    /// Removes the entry with `id`, returning it.
+    pub fn remove(&self, id: u64) -> Result<Command, Box<dyn std::error::Error>> {
+        let mut data = self.load_data()?;
+        let index = data.commands
+            .iter()
+            .position(|c| c.id == id)
+            .ok_or_else(|| format!("History entry {} not found", id))?;
+        let removed = data.commands.remove(index);
+        self.save(&data)?;
+        Ok(removed)
+    }
+
+    /// Entries whose command or description matches the regex `pattern`.
+    pub fn find_matching(&self, pattern: &str) -> Result<Vec<Command>, Box<dyn std::error::Error>> {
+        let regex = regex::Regex::new(pattern)
+            .map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+        let mut commands = self.list(None)?;
+        commands.retain(|c| regex.is_match(&c.command) || regex.is_match(&c.description));
+        Ok(commands)
+    }
+
+    /// Removes all entries whose command or description matches the regex
+    /// `pattern`, returning them.
+    pub fn remove_matching(&self, pattern: &str) -> Result<Vec<Command>, Box<dyn std::error::Error>> {
+        let matched = self.find_matching(pattern)?;
+        let ids: HashSet<u64> = matched.iter().map(|c| c.id).collect();
+
+        let mut data = self.load_data()?;
+        data.commands.retain(|c| !ids.contains(&c.id));
+        self.save(&data)?;
+        Ok(matched)
+    }
+
+    /// Adds entries in bulk, in the order given, skipping ones already
+    /// recorded so re-importing the same file is a no-op. An imported repeat
+    /// of an existing entry only adds to its usage; the entry keeps its own
+    /// metadata and source. Entries with a `0` timestamp are dated just before
+    /// the next dated entry, or before import time, keeping their order.
+    /// Returns the number of entries recorded and not pruned right away.
+    pub fn import(&self, commands: Vec<Command>) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut data = self.load_data()?;
+        let mut added = 0;
+
+        let mut next = chrono::Utc::now().timestamp();
+        let mut commands: Vec<(bool, Command)> = commands
+            .into_iter()
+            .rev()
+            .map(|mut command| {
+                let undated = command.timestamp == 0;
+                if undated {
+                    next -= 1;
+                    command.timestamp = next;
+                } else {
+                    next = command.timestamp;
+                }
+                (undated, command)
+            })
+            .collect();
+        commands.reverse();
+
+        let existing = data.commands.len();
+        let first_new_id = data.next_id;
+        let mut duplicates = self.dedup_index(&data);
+        // Without dedup, only the exact same imported line is skipped
+        let mut seen: HashSet<(i64, String, Option<String>)> = HashSet::new();
+        let mut recorded: HashSet<(String, Option<String>)> = HashSet::new();
+        if !self.dedup {
+            for c in &data.commands {
+                seen.insert((c.timestamp, c.command.clone(), c.source.clone()));
+                recorded.insert((c.command.clone(), c.source.clone()));
+            }
+        }
+
+        commands.sort_by_key(|(_, c)| c.timestamp);
+        for (undated, command) in commands {
+            let mut command = self.redact(command);
+            let normalized = command.normalized_command();
+            match duplicates.get(&normalized) {
+                // Occurrences up to the last recorded use were already counted,
+                // and an undated one can't be told apart from those
+                Some(&i) if undated && i < existing => continue,
+                Some(&i) if command.timestamp <= data.commands[i].last_used() => continue,
+                Some(&i) => count_use(&mut data.commands[i], &command),
+                None => {
+                    if !self.dedup {
+                        let known = if undated {
+                            recorded.contains(&(command.command.clone(), command.source.clone()))
+                        } else {
+                            !seen.insert((command.timestamp, command.command.clone(), command.source.clone()))
+                        };
+                        if known {
+                            continue;
+                        }
+                    }
+                    command.id = data.next_id;
+                    data.next_id += 1;
+                    if self.dedup {
+                        duplicates.insert(normalized, data.commands.len());
+                    }
+                    data.commands.push(command);
+                }
+            }
+            added += 1;
+        }
+
+        data.commands.sort_by_key(|c| c.timestamp);
+        let pruned = self.apply_retention(&mut data)?;
+        self.save(&data)?;
+        Ok(added - pruned.iter().filter(|c| c.id >= first_new_id).count())
+    }
+
     pub fn list(&self, limit: Option<usize>) -> Result<Vec<Command>, Box<dyn std::error::Error>> {
+        self.list_by(HistorySort::Recent, limit)
+    }
+
+    pub fn list_by(&self, order: HistorySort, limit: Option<usize>) -> Result<Vec<Command>, Box<dyn std::error::Error>> {
         let data = self.load_data()?;
         let mut commands = data.commands;
         
-        // Sort by timestamp, newest first
-        commands.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        sort_commands(&mut commands, order);
         
         if let Some(n) = limit {
             commands.truncate(n);
@@ -54,34 +389,177 @@ impl History {
     }
 
     pub fn search(&self, query: &str) -> Result<Vec<Command>, Box<dyn std::error::Error>> {
-        let data = self.load_data()?;
-        let query_lower = query.to_lowercase();
-        
-        let mut results: Vec<Command> = data.commands
+        self.search_by(query, HistorySort::Recent)
+    }
+
+    /// Runs a search query (see `Query`), best matches first and ties broken
+    /// by `order`.
+    pub fn search_by(&self, query: &str, order: HistorySort) -> Result<Vec<Command>, Box<dyn std::error::Error>> {
+        self.query(&Query::parse(query, false)?, order)
+    }
+
+    pub fn query(&self, query: &Query, order: HistorySort) -> Result<Vec<Command>, Box<dyn std::error::Error>> {
+        let mut commands = self.load_data()?.commands;
+        sort_commands(&mut commands, order);
+        Ok(query.rank(commands))
+    }
+
+    /// Past entries whose description resembles `description`, most similar
+    /// first. Entries that ran successfully without edits rank higher, and
+    /// entries that failed are skipped.
+    pub fn similar(&self, description: &str, limit: usize) -> Result<Vec<Command>, Box<dyn std::error::Error>> {
+        let words = description_words(description);
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut scored: Vec<(f64, Command)> = self.list(None)?
             .into_iter()
-            .filter(|cmd| {
-                cmd.description.to_lowercase().contains(&query_lower)
-                    || cmd.command.to_lowercase().contains(&query_lower)
+            .filter(|c| !c.description.is_empty() && c.exit_code.is_none_or(|code| code == 0))
+            .filter_map(|c| {
+                let similarity = jaccard(&words, &description_words(&c.description));
+                if similarity < 0.5 {
+                    return None;
+                }
+                let trusted = c.exit_code == Some(0) && !c.edited;
+                Some((if trusted { similarity } else { similarity * 0.8 }, c))
             })
             .collect();
-        
-        // Sort by timestamp, newest first
-        results.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-        
-        Ok(results)
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut seen = HashSet::new();
+        Ok(scored
+            .into_iter()
+            .map(|(_, c)| c)
+            .filter(|c| seen.insert(c.normalized_command()))
+            .take(limit)
+            .collect())
     }
 
-    fn load_data(&self) -> Result<HistoryData, Box<dyn std::error::Error>> {
-        match self.load::<HistoryData>() {
-            Ok(data) => Ok(data),
-            Err(_) => Ok(HistoryData::default()),
+    /// Returns entries matching `filter`, newest first.
+    pub fn filter(&self, filter: &HistoryFilter, limit: Option<usize>) -> Result<Vec<Command>, Box<dyn std::error::Error>> {
+        let mut commands = self.list(None)?;
+        commands.retain(|cmd| filter.matches(cmd));
+
+        if let Some(n) = limit {
+            commands.truncate(n);
+        }
+
+        Ok(commands)
+    }
+
+    /// Removes entries dropped by the retention policy, returning them oldest
+    /// first. With `dry_run` the history file is left untouched.
+    pub fn prune(&self, dry_run: bool) -> Result<Vec<Command>, Box<dyn std::error::Error>> {
+        let mut data = self.load_data()?;
+        let pruned = self.apply_retention(&mut data)?;
+        if !dry_run && !pruned.is_empty() {
+            self.save(&data)?;
+        }
+        Ok(pruned)
+    }
+
+    fn apply_retention(&self, data: &mut HistoryData) -> Result<Vec<Command>, Box<dyn std::error::Error>> {
+        let now = chrono::Utc::now().timestamp();
+        let mut indices = self.retention.select_pruned(&data.commands, &HashSet::new(), now);
+        // Protecting entries only ever prunes less, so bookmarks are loaded
+        // only once something would actually go
+        if !indices.is_empty() && self.retention.keep_bookmarked {
+            if let Some(bookmarks) = &self.bookmarks {
+                let bookmarked: HashSet<String> =
+                    bookmarks.list(&TagFilter::default())?.into_iter().map(|b| b.command).collect();
+                indices = self.retention.select_pruned(&data.commands, &bookmarked, now);
+            }
+        }
+        let indices: HashSet<usize> = indices.into_iter().collect();
+
+        let mut pruned = Vec::new();
+        let mut kept = Vec::with_capacity(data.commands.len());
+        for (i, command) in data.commands.drain(..).enumerate() {
+            if indices.contains(&i) {
+                pruned.push(command);
+            } else {
+                kept.push(command);
+            }
+        }
+        data.commands = kept;
+
+        pruned.sort_by_key(|c| c.timestamp);
+        Ok(pruned)
+    }
+
+    /// Maps each normalized command to the index of its entry, so a batch
+    /// normalizes every stored entry only once. Empty when dedup is off.
+    fn dedup_index(&self, data: &HistoryData) -> HashMap<String, usize> {
+        let mut index = HashMap::new();
+        if self.dedup {
+            for (i, command) in data.commands.iter().enumerate() {
+                index.entry(command.normalized_command()).or_insert(i);
+            }
         }
+        index
     }
+
+    fn redact(&self, mut command: Command) -> Command {
+        command.description = self.redactor.redact(&command.description);
+        command.command = self.redactor.redact(&command.command);
+        command.suggestions = command.suggestions.iter().map(|s| self.redactor.redact(s)).collect();
+        command
+    }
+
+    fn load_data(&self) -> Result<HistoryData, Box<dyn std::error::Error>> {
+        let mut data = if self.get_storage_path().exists() {
+            self.load::<HistoryData>()?
+        } else {
+            HistoryData::default()
+        };
+        data.assign_ids();
+        Ok(data)
+    }
+}
+
+fn description_words(text: &str) -> HashSet<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// Folds a repeated command into its existing entry: the newest metadata wins,
+/// while the first-seen timestamp and star are kept and the count is bumped.
+fn merge_into(existing: &mut Command, mut command: Command) {
+    command.last_used = Some(command.last_used().max(existing.last_used()));
+    command.timestamp = existing.timestamp.min(command.timestamp);
+    command.count += existing.count;
+    command.starred |= existing.starred;
+    command.id = existing.id;
+    *existing = command;
+}
+
+/// Records an imported occurrence of an existing entry without replacing its
+/// metadata, so a shai entry isn't turned into an imported one.
+fn count_use(existing: &mut Command, command: &Command) {
+    existing.last_used = Some(command.last_used().max(existing.last_used()));
+    existing.timestamp = existing.timestamp.min(command.timestamp);
+    existing.count += command.count;
 }
 
 impl Storage for History {
     fn get_storage_path(&self) -> PathBuf {
-        get_data_dir().join("history.json")
+        self.path.clone()
+    }
+
+    fn get_encryption(&self) -> Option<&Encryption> {
+        self.encryption.as_ref()
     }
 }
 
@@ -1,97 +1,412 @@
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use tempfile::TempDir;
-
-    // Helper function to create a test history with custom storage path
-    fn setup_test_history() -> (History, TempDir) {
-        let temp_dir = TempDir::new().unwrap();
-        let history = History::new();
-        
-        // Override storage path for testing
-        std::env::set_var("HOME", temp_dir.path().to_str().unwrap());
-        
-        (history, temp_dir)
-    }
+use crate::{Command, Encryption, History, HistoryFilter, HistorySort, RetentionPolicy};
+use tempfile::TempDir;
+
+// Helper function to create a test history backed by a temporary file
+fn setup_test_history() -> (History, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let history = History::with_path(temp_dir.path().join("history.json"));
+
+    (history, temp_dir)
+}
+
+#[test]
+fn test_add_command() {
+    let (history, _temp_dir) = setup_test_history();
+    
+    let command = Command::new(
+        "list files".to_string(),
+        "ls -la".to_string(),
+    );
+    
+    let result = history.add(command);
+    assert!(result.is_ok());
+}
 
-    #[test]
-    fn test_add_command() {
-        let (history, _temp_dir) = setup_test_history();
-        
+#[test]
+fn test_list_commands() {
+    let (history, _temp_dir) = setup_test_history();
+    
+    // Add some commands
+    for i in 0..5 {
         let command = Command::new(
-            "list files".to_string(),
-            "ls -la".to_string(),
+            format!("command {}", i),
+            format!("echo {}", i),
         );
-        
-        let result = history.add(command);
-        assert!(result.is_ok());
+        history.add(command).unwrap();
     }
+    
+    let commands = history.list(Some(3)).unwrap();
+    assert_eq!(commands.len(), 3);
+}
 
-    #[test]
-    fn test_list_commands() {
-        let (history, _temp_dir) = setup_test_history();
-        
-        // Add some commands
-        for i in 0..5 {
-            let command = Command::new(
-                format!("command {}", i),
-                format!("echo {}", i),
-            );
-            history.add(command).unwrap();
-        }
-        
-        let commands = history.list(Some(3)).unwrap();
-        assert_eq!(commands.len(), 3);
-    }
+#[test]
+fn test_search_commands() {
+    let (history, _temp_dir) = setup_test_history();
+    
+    // Add commands with different descriptions
+    let command1 = Command::new("find docker images".to_string(), "docker images".to_string());
+    let command2 = Command::new("list files".to_string(), "ls -la".to_string());
+    let command3 = Command::new("remove docker container".to_string(), "docker rm".to_string());
+    
+    history.add(command1).unwrap();
+    history.add(command2).unwrap();
+    history.add(command3).unwrap();
+    
+    let results = history.search("docker").unwrap();
+    assert_eq!(results.len(), 2);
+}
+
+#[test]
+fn test_clear_history() {
+    let (history, _temp_dir) = setup_test_history();
+    
+    // Add a command
+    let command = Command::new("test".to_string(), "echo test".to_string());
+    history.add(command).unwrap();
+    
+    // Clear history
+    history.clear().unwrap();
+    
+    let commands = history.list(None).unwrap();
+    assert_eq!(commands.len(), 0);
+}
 
-    #[test]
-    fn test_search_commands() {
-        let (history, _temp_dir) = setup_test_history();
-        
-        // Add commands with different descriptions
-        let command1 = Command::new("find docker images".to_string(), "docker images".to_string());
-        let command2 = Command::new("list files".to_string(), "ls -la".to_string());
-        let command3 = Command::new("remove docker container".to_string(), "docker rm".to_string());
-        
-        history.add(command1).unwrap();
-        history.add(command2).unwrap();
-        history.add(command3).unwrap();
-        
-        let results = history.search("docker").unwrap();
-        assert_eq!(results.len(), 2);
+#[test]
+fn test_history_limit() {
+    let (history, _temp_dir) = setup_test_history();
+    
+    // Add more than 1000 commands
+    for i in 0..1100 {
+        let command = Command::new(
+            format!("command {}", i),
+            format!("echo {}", i),
+        );
+        history.add(command).unwrap();
     }
+    
+    let commands = history.list(None).unwrap();
+    assert!(commands.len() <= 1000);
+}
+
+#[test]
+fn test_encrypted_history_roundtrip() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("history.json");
+    let history = History::with_path(&path)
+        .with_encryption(Encryption::from_passphrase("correct horse"));
+
+    history.add(Command::new("show token".to_string(), "echo s3cr3t".to_string())).unwrap();
+
+    let raw = std::fs::read_to_string(&path).unwrap();
+    assert!(!raw.contains("s3cr3t"));
+
+    let commands = history.list(None).unwrap();
+    assert_eq!(commands[0].command, "echo s3cr3t");
+}
+
+#[test]
+fn test_encrypted_history_wrong_passphrase() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("history.json");
+    History::with_path(&path)
+        .with_encryption(Encryption::from_passphrase("correct horse"))
+        .add(Command::new("test".to_string(), "echo test".to_string()))
+        .unwrap();
+
+    let wrong = History::with_path(&path)
+        .with_encryption(Encryption::from_passphrase("battery staple"));
+    assert!(wrong.list(None).is_err());
+    assert!(History::with_path(&path).list(None).is_err());
+}
+
+#[test]
+fn test_add_redacts_secrets() {
+    let (history, _temp_dir) = setup_test_history();
+
+    history.add(Command::new("call api".to_string(), "curl -H 'Authorization: Bearer s3cr3t' api".to_string())).unwrap();
+
+    let commands = history.list(None).unwrap();
+    assert_eq!(commands[0].command, "curl -H 'Authorization: Bearer [REDACTED]' api");
+}
+
+#[test]
+fn test_filter_by_metadata() {
+    let (history, _temp_dir) = setup_test_history();
+
+    let mut here = Command::new("list".to_string(), "ls".to_string())
+        .with_model("model-a", "https://example.com")
+        .with_edited(true);
+    here.cwd = Some("/project".to_string());
+    history.add(here).unwrap();
+    history.add(Command::new("disk".to_string(), "df -h".to_string()).with_model("model-b", "https://example.com")).unwrap();
+
+    let filter = HistoryFilter { cwd: Some("/project".to_string()), ..Default::default() };
+    assert_eq!(history.filter(&filter, None).unwrap().len(), 1);
+
+    let filter = HistoryFilter { model: Some("model-b".to_string()), ..Default::default() };
+    assert_eq!(history.filter(&filter, None).unwrap()[0].command, "df -h");
+
+    let filter = HistoryFilter { edited: true, ..Default::default() };
+    assert_eq!(history.filter(&filter, None).unwrap()[0].command, "ls");
+}
+
+#[test]
+fn test_import_skips_duplicates() {
+    let (history, _temp_dir) = setup_test_history();
+
+    let imported = crate::Shell::Zsh.parse(b": 1700000000:0;ls\n: 1700000001:0;pwd\n");
+    assert_eq!(history.import(imported.clone()).unwrap(), 2);
+    assert_eq!(history.import(imported).unwrap(), 0);
+
+    let commands = history.list(None).unwrap();
+    assert_eq!(commands.len(), 2);
+    assert_eq!(commands[0].command, "pwd");
+}
+
+#[test]
+fn test_reimport_bash_without_timestamps() {
+    let (history, _temp_dir) = setup_test_history();
+    history.add(Command::new("list".to_string(), "ls".to_string())).unwrap();
+
+    let content = b"ls\ncargo build\ncargo test\n";
+    // "ls" has no timestamp, so it can't be told apart from the recorded use
+    assert_eq!(history.import(crate::Shell::Bash.parse(content)).unwrap(), 2);
+    assert_eq!(history.import(crate::Shell::Bash.parse(content)).unwrap(), 0);
+
+    let commands = history.list(None).unwrap();
+    assert_eq!(commands.len(), 3);
+    let ls = commands.iter().find(|c| c.command == "ls").unwrap();
+    assert_eq!(ls.count, 1);
+    assert_eq!(ls.description, "list");
+    assert!(ls.source.is_none());
+}
+
+#[test]
+fn test_import_dates_entries_without_timestamps() {
+    let (history, _temp_dir) = setup_test_history();
+    let history = history.with_retention(RetentionPolicy { max_age_days: Some(90), ..Default::default() });
+
+    let content = b"cargo build\n#1700000000\ncargo test\ncargo run\n";
+    assert_eq!(history.import(crate::Shell::Bash.parse(content)).unwrap(), 1);
+
+    // "cargo build" is dated just before 2023's "cargo test", so age
+    // retention drops both, while "cargo run" counts as recent
+    let commands = history.list(None).unwrap();
+    assert_eq!(commands.len(), 1);
+    assert_eq!(commands[0].command, "cargo run");
+    assert!(commands[0].timestamp > chrono::Utc::now().timestamp() - 60);
+}
+
+#[test]
+fn test_import_does_not_evict_native_entries() {
+    let (history, _temp_dir) = setup_test_history();
+    let history = history.with_retention(RetentionPolicy { max_entries: Some(3), ..Default::default() });
+    history.add(Command::new("build".to_string(), "cargo build".to_string())).unwrap();
+    history.add(Command::new("test".to_string(), "cargo test".to_string())).unwrap();
 
-    #[test]
-    fn test_clear_history() {
-        let (history, _temp_dir) = setup_test_history();
-        
-        // Add a command
-        let command = Command::new("test".to_string(), "echo test".to_string());
+    let mut imported = crate::Shell::Zsh.parse(b"echo one\necho two\n");
+    imported.extend(crate::Shell::Zsh.parse(format!(": {}:0;echo three\n", chrono::Utc::now().timestamp() + 60).as_bytes()));
+    history.import(imported).unwrap();
+
+    let mut commands: Vec<String> = history.list(None).unwrap().into_iter().map(|c| c.command).collect();
+    commands.sort();
+    assert_eq!(commands, vec!["cargo build", "cargo test", "echo three"]);
+}
+
+#[test]
+fn test_prune_keeps_starred_and_bookmarked() {
+    let temp_dir = TempDir::new().unwrap();
+    let bookmarks = crate::Bookmark::with_path(temp_dir.path().join("bookmarks.json"));
+    bookmarks.add(crate::BookmarkItem {
+        name: "list".to_string(),
+        command: "ls -la".to_string(),
+        description: String::new(),
+        tags: Vec::new(),
+        created_at: 0,
+        ..Default::default()
+    }).unwrap();
+
+    let history = History::with_path(temp_dir.path().join("history.json"))
+        .with_bookmarks(bookmarks)
+        .with_retention(RetentionPolicy { max_entries: None, ..Default::default() });
+
+    let mut old_starred = Command::new("old".to_string(), "echo starred".to_string());
+    old_starred.timestamp = 100;
+    old_starred.starred = true;
+    let mut old_bookmarked = Command::new("old".to_string(), "ls -la".to_string());
+    old_bookmarked.timestamp = 200;
+    let mut old_plain = Command::new("old".to_string(), "echo plain".to_string());
+    old_plain.timestamp = 300;
+    for command in [old_starred, old_bookmarked, old_plain] {
         history.add(command).unwrap();
-        
-        // Clear history
-        history.clear().unwrap();
-        
-        let commands = history.list(None).unwrap();
-        assert_eq!(commands.len(), 0);
     }
+    history.add(Command::new("new".to_string(), "echo new".to_string())).unwrap();
+
+    let history = history.with_retention(RetentionPolicy { max_age_days: Some(30), max_entries: None, ..Default::default() });
+
+    let pruned = history.prune(true).unwrap();
+    assert_eq!(pruned.len(), 1);
+    assert_eq!(pruned[0].command, "echo plain");
+    assert_eq!(history.list(None).unwrap().len(), 4);
+
+    history.prune(false).unwrap();
+    assert_eq!(history.list(None).unwrap().len(), 3);
+}
+
+#[test]
+fn test_add_loads_bookmarks_only_when_pruning() {
+    let temp_dir = TempDir::new().unwrap();
+    let bookmarks_path = temp_dir.path().join("bookmarks.json");
+    std::fs::write(&bookmarks_path, "not json").unwrap();
+    let history = History::with_path(temp_dir.path().join("history.json"))
+        .with_bookmarks(crate::Bookmark::with_path(&bookmarks_path))
+        .with_retention(RetentionPolicy { max_entries: Some(1), ..Default::default() });
+
+    history.add(Command::new("list".to_string(), "ls".to_string())).unwrap();
+    assert!(history.add(Command::new("print".to_string(), "pwd".to_string())).is_err());
+}
 
-    #[test]
-    fn test_history_limit() {
-        let (history, _temp_dir) = setup_test_history();
-        
-        // Add more than 1000 commands
-        for i in 0..1100 {
-            let command = Command::new(
-                format!("command {}", i),
-                format!("echo {}", i),
-            );
-            history.add(command).unwrap();
-        }
-        
-        let commands = history.list(None).unwrap();
-        assert!(commands.len() <= 1000);
+#[test]
+fn test_retention_max_entries_and_size() {
+    let commands: Vec<Command> = (0..5)
+        .map(|i| {
+            let mut command = Command::new(format!("command {}", i), format!("echo {}", i));
+            command.timestamp = i;
+            command
+        })
+        .collect();
+    let bookmarked = std::collections::HashSet::new();
+
+    let policy = RetentionPolicy { max_entries: Some(3), ..Default::default() };
+    assert_eq!(policy.select_pruned(&commands, &bookmarked, 10), vec![0, 1]);
+
+    let entry_size = serde_json::to_string(&commands[0]).unwrap().len() as u64;
+    let policy = RetentionPolicy { max_entries: None, max_bytes: Some(entry_size * 2), ..Default::default() };
+    assert_eq!(policy.select_pruned(&commands, &bookmarked, 10), vec![0, 1, 2]);
+}
+
+#[test]
+fn test_dedup_bumps_count() {
+    let (history, _temp_dir) = setup_test_history();
+
+    let mut first = Command::new("list".to_string(), "ls  -la".to_string());
+    first.timestamp = 100;
+    history.add(first).unwrap();
+    history.add(Command::new("list files".to_string(), "ls -la".to_string())).unwrap();
+
+    let commands = history.list(None).unwrap();
+    assert_eq!(commands.len(), 1);
+    assert_eq!(commands[0].count, 2);
+    assert_eq!(commands[0].timestamp, 100);
+    assert!(commands[0].last_used() > 100);
+    assert_eq!(commands[0].description, "list files");
+}
+
+#[test]
+fn test_update_merges_duplicate() {
+    let (history, _temp_dir) = setup_test_history();
+
+    let mut old = Command::new("status".to_string(), "git status".to_string());
+    old.timestamp = 100;
+    old.starred = true;
+    history.add(old).unwrap();
+    let id = history.add(Command::new("status".to_string(), "git stauts".to_string())).unwrap();
+
+    history.update(id, |c| c.command = "git  status".to_string()).unwrap();
+
+    let commands = history.list(None).unwrap();
+    assert_eq!(commands.len(), 1);
+    assert_eq!(commands[0].id, id);
+    assert_eq!(commands[0].count, 2);
+    assert_eq!(commands[0].timestamp, 100);
+    assert!(commands[0].starred);
+}
+
+#[test]
+fn test_dedup_disabled() {
+    let (history, _temp_dir) = setup_test_history();
+    let history = history.with_dedup(false);
+
+    history.add(Command::new("list".to_string(), "ls".to_string())).unwrap();
+    history.add(Command::new("list".to_string(), "ls".to_string())).unwrap();
+
+    assert_eq!(history.list(None).unwrap().len(), 2);
+}
+
+#[test]
+fn test_list_by_frecency() {
+    let (history, _temp_dir) = setup_test_history();
+
+    for _ in 0..3 {
+        history.add(Command::new("status".to_string(), "git status".to_string())).unwrap();
     }
+    history.add(Command::new("list".to_string(), "ls".to_string())).unwrap();
+
+    let recent = history.list_by(HistorySort::Recent, None).unwrap();
+    let frecent = history.list_by(HistorySort::Frecency, None).unwrap();
+    assert_eq!(frecent[0].command, "git status");
+    assert_eq!(frecent.len(), recent.len());
+}
+
+#[test]
+fn test_ids_are_stable() {
+    let (history, _temp_dir) = setup_test_history();
+
+    let first = history.add(Command::new("list".to_string(), "ls".to_string())).unwrap();
+    let second = history.add(Command::new("disk".to_string(), "df -h".to_string())).unwrap();
+    assert_ne!(first, second);
+
+    history.remove(first).unwrap();
+    let third = history.add(Command::new("where".to_string(), "pwd".to_string())).unwrap();
+    assert!(third > second);
+    assert_eq!(history.get(second).unwrap().unwrap().command, "df -h");
+
+    // Re-recording a command keeps its id
+    assert_eq!(history.add(Command::new("disk".to_string(), "df -h".to_string())).unwrap(), second);
 }
 
+#[test]
+fn test_remove_matching() {
+    let (history, _temp_dir) = setup_test_history();
+
+    history.add(Command::new("login".to_string(), "login --user bob".to_string())).unwrap();
+    history.add(Command::new("login".to_string(), "login --user alice".to_string())).unwrap();
+    history.add(Command::new("list".to_string(), "ls".to_string())).unwrap();
+
+    let removed = history.remove_matching("--user (bob|alice)").unwrap();
+    assert_eq!(removed.len(), 2);
+    assert_eq!(history.list(None).unwrap().len(), 1);
+    assert!(history.remove_matching("(").is_err());
+}
+
+#[test]
+fn test_update_redacts() {
+    let (history, _temp_dir) = setup_test_history();
+
+    let id = history.add(Command::new("call".to_string(), "curl api".to_string())).unwrap();
+    history.update(id, |c| c.command = "curl --token=abc123 api".to_string()).unwrap();
+
+    assert_eq!(history.get(id).unwrap().unwrap().command, "curl --token=[REDACTED] api");
+    assert!(history.update(id + 100, |_| {}).is_err());
+}
+
+#[test]
+fn test_similar_prefers_successful_entries() {
+    let (history, _temp_dir) = setup_test_history();
+
+    let mut failed = Command::new("show disk usage".to_string(), "du -x".to_string());
+    failed.exit_code = Some(1);
+    history.add(failed).unwrap();
+    let mut edited = Command::new("show the disk usage please".to_string(), "df".to_string()).with_edited(true);
+    edited.exit_code = Some(0);
+    history.add(edited).unwrap();
+    let mut trusted = Command::new("show disk usage".to_string(), "df -h".to_string());
+    trusted.exit_code = Some(0);
+    history.add(trusted).unwrap();
+    history.add(Command::new("list files".to_string(), "ls".to_string())).unwrap();
+
+    let similar = history.similar("Show the disk usage", 5).unwrap();
+    let commands: Vec<&str> = similar.iter().map(|c| c.command.as_str()).collect();
+    assert_eq!(commands, vec!["df -h", "df"]);
+    assert!(history.similar("", 5).unwrap().is_empty());
+}
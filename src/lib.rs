@@ -4,11 +4,65 @@ pub mod storage;
 pub mod history;
 pub mod config;
 pub mod bookmark;
+pub mod redact;
+pub mod stats;
+pub mod shell_history;
+pub mod export;
+pub mod retention;
+pub mod query;
+pub mod cache;
+pub mod template;
+pub mod collection;
+pub mod cheatsheet;
+pub mod semantic;
+pub mod markdown;
+
+#[cfg(test)]
+mod storage_tests;
+#[cfg(test)]
+mod history_tests;
+#[cfg(test)]
+mod bookmark_tests;
+#[cfg(test)]
+mod redact_tests;
+#[cfg(test)]
+mod stats_tests;
+#[cfg(test)]
+mod shell_history_tests;
+#[cfg(test)]
+mod export_tests;
+#[cfg(test)]
+mod query_tests;
+#[cfg(test)]
+mod cache_tests;
+#[cfg(test)]
+mod ai_tests;
+#[cfg(test)]
+mod template_tests;
+#[cfg(test)]
+mod collection_tests;
+#[cfg(test)]
+mod cheatsheet_tests;
+#[cfg(test)]
+mod semantic_tests;
+#[cfg(test)]
+mod markdown_tests;
 
 pub use command::{Command, Suggestion};
-pub use ai::{get_command_suggestion, generate_bookmark_info, BookmarkMetadata};
-pub use storage::Storage;
-pub use history::History;
+pub use ai::{get_command_suggestion, parse_command, generate_bookmark_info, explain_command, BookmarkMetadata};
+pub use storage::{Storage, Encryption};
+pub use history::{History, HistoryFilter, HistorySort, parse_date};
 pub use config::Config;
-pub use bookmark::{Bookmark, BookmarkItem};
+pub use bookmark::{Bookmark, BookmarkItem, TagFilter, BookmarkEdit, ConflictPolicy, ImportSummary};
+pub use redact::Redactor;
+pub use stats::HistoryStats;
+pub use shell_history::Shell;
+pub use export::ExportFormat;
+pub use retention::RetentionPolicy;
+pub use query::Query;
+pub use cache::ResponseCache;
+pub use template::Placeholder;
+pub use collection::BookmarkSource;
+pub use cheatsheet::CheatFormat;
+pub use semantic::{Embedder, EmbeddingIndex};
 
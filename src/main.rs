@@ -1,7 +1,11 @@
 use clap::{Parser, Subcommand};
-use inquire::{Select, Text};
+use inquire::{Confirm, Select, Text};
+use inquire::validator::Validation;
 use openai_api_rs::v1::api::OpenAIClient;
-use shai::{Config, History, Suggestion, Command, get_command_suggestion, Bookmark, BookmarkItem, generate_bookmark_info};
+use shai::{Config, Embedder, EmbeddingIndex, TagFilter, ResponseCache, Placeholder, BookmarkEdit, CheatFormat, ConflictPolicy, History, HistoryFilter, HistorySort, Query, HistoryStats, Shell, ExportFormat, parse_date, Suggestion, Command, get_command_suggestion, parse_command, Bookmark, BookmarkItem, generate_bookmark_info, explain_command};
+use shai::command::{copy_to_clipboard, run_script};
+use shai::export::{export_history, export_bookmarks, export_aliases};
+use shai::bookmark::{normalize_tags, tag_from_text};
 
 #[derive(Parser)]
 #[command(name = "shai")]
@@ -15,6 +19,10 @@ struct Cli {
     #[arg(short, long, global = true)]
     verbose: bool,
 
+    /// Always ask the model instead of reusing cached responses
+    #[arg(long, global = true)]
+    no_cache: bool,
+
     #[command(subcommand)]
     command: Option<CliCommand>,
 }
@@ -23,23 +31,180 @@ struct Cli {
 enum CliCommand {
     /// View and manage command history
     History {
+        #[command(subcommand)]
+        action: Option<HistoryAction>,
+
         /// Number of recent commands to show
         #[arg(short, long, default_value = "2")]
         limit: usize,
         
-        /// Search for commands containing this text
+        /// Search query: words, quoted phrases, `cmd:`/`desc:`/`cwd:`/`model:`
+        /// fields, `/regex/` and `!negated` terms
         #[arg(short, long)]
         search: Option<String>,
+
+        /// Treat every search term as a regular expression
+        #[arg(long, requires = "search")]
+        regex: bool,
+
+        /// Only commands on or after this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only commands on or before this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Only commands that were executed
+        #[arg(long)]
+        executed: bool,
+
+        /// Only commands that exited with a non-zero status
+        #[arg(long)]
+        failed: bool,
         
         /// Clear all history
         #[arg(short, long)]
         clear: bool,
+
+        /// Only show commands run from the current directory
+        #[arg(long)]
+        here: bool,
+
+        /// Only show commands suggested by this model
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Only show commands that were edited before running
+        #[arg(long)]
+        edited: bool,
+
+        /// Order of the listed commands
+        #[arg(long, value_enum, default_value = "recent")]
+        sort: HistorySort,
     },
     /// Manage command bookmarks
     Bookmark {
         #[command(subcommand)]
         action: BookmarkAction,
     },
+    /// Inspect or clear the local cache of AI responses
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Show the number of cached entries and responses and their age
+    Stats,
+    /// Remove all cached responses
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum HistoryAction {
+    /// Fuzzy-pick a history entry to run, edit, copy, bookmark, explain or star
+    Pick,
+    /// Show usage statistics and insights
+    Stats {
+        /// Number of entries to show in each ranking
+        #[arg(short, long, default_value = "10")]
+        top: usize,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Import commands from a shell's native history file
+    Import {
+        /// Shell whose history format to read
+        #[arg(long, value_enum)]
+        from: Shell,
+
+        /// History file to read (defaults to the shell's standard location)
+        #[arg(short, long)]
+        file: Option<std::path::PathBuf>,
+    },
+    /// Export history to JSON Lines, CSV, Markdown or a shell script
+    Export {
+        #[arg(short, long, value_enum, default_value = "jsonl")]
+        format: ExportFormat,
+
+        /// Only entries on or after this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only entries on or before this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Only entries run from a bookmark with this tag or one of its
+        /// `tag/...` children (repeatable or comma-separated; all must match)
+        #[arg(short, long, value_delimiter = ',')]
+        tag: Vec<String>,
+
+        /// Match bookmarks with any of the tags instead of all of them
+        #[arg(long, requires = "tag")]
+        any: bool,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Remove entries according to the retention policy
+    Prune {
+        /// Show what would be removed without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Remove history entries by id or by pattern
+    Rm {
+        /// Ids of the entries to remove
+        #[arg(required_unless_present = "pattern")]
+        ids: Vec<u64>,
+
+        /// Remove every entry whose command or description matches this regex
+        #[arg(short = 'm', long = "match", conflicts_with = "ids")]
+        pattern: Option<String>,
+
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Edit the command or description of a history entry
+    Edit {
+        id: u64,
+
+        /// New command text (prompts when neither option is given)
+        #[arg(short, long)]
+        command: Option<String>,
+
+        /// New description
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TagAction {
+    /// List tags with the number of bookmarks carrying them (default)
+    List,
+    /// Rename a tag, along with its `tag/...` children
+    Rename {
+        from: String,
+        to: String,
+    },
+    /// Replace several tags, and their children, with one tag
+    Merge {
+        #[arg(required = true)]
+        tags: Vec<String>,
+
+        /// Tag to merge into
+        #[arg(long)]
+        into: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -48,17 +213,42 @@ enum BookmarkAction {
     Add {
         #[arg(short, long)]
         name: String,
-        #[arg(short, long)]
-        command: String,
+        #[arg(short, long, required_unless_present = "file")]
+        command: Option<String>,
+        /// Read the command or script from this file
+        #[arg(short, long, conflicts_with = "command")]
+        file: Option<std::path::PathBuf>,
         #[arg(short, long)]
         description: Option<String>,
-        #[arg(short, long)]
+        /// Tags, comma-separated or repeated
+        #[arg(short, long, value_delimiter = ',')]
         tags: Vec<String>,
+        /// Run the command as a script with this interpreter, e.g. `python3`
+        /// (default for --file: the script's #! line)
+        #[arg(short, long)]
+        interpreter: Option<String>,
+        /// Markdown notes
+        #[arg(long)]
+        notes: Option<String>,
+        /// Example output (repeatable)
+        #[arg(long = "example")]
+        examples: Vec<String>,
     },
     /// List all bookmarks
     List {
-        #[arg(short, long)]
-        tag: Option<String>,
+        /// Only bookmarks with this tag or one of its `tag/...` children
+        /// (repeatable or comma-separated; all must match)
+        #[arg(short, long, value_delimiter = ',')]
+        tag: Vec<String>,
+
+        /// Match bookmarks with any of the tags instead of all of them
+        #[arg(long, requires = "tag")]
+        any: bool,
+    },
+    /// List tags with bookmark counts, or rename and merge them
+    Tags {
+        #[command(subcommand)]
+        action: Option<TagAction>,
     },
     /// Get a specific bookmark
     Get {
@@ -68,45 +258,229 @@ enum BookmarkAction {
     Remove {
         name: String,
     },
+    /// Edit a bookmark with field options, or in $EDITOR when none are given
+    Edit {
+        name: String,
+
+        /// New name for the bookmark
+        #[arg(long)]
+        rename: Option<String>,
+
+        #[arg(short, long)]
+        command: Option<String>,
+
+        #[arg(short, long)]
+        description: Option<String>,
+
+        /// Tag to add (repeatable)
+        #[arg(long)]
+        add_tag: Vec<String>,
+
+        /// Tag to remove (repeatable)
+        #[arg(long)]
+        remove_tag: Vec<String>,
+
+        /// Interpreter for script bookmarks; empty to run in the shell again
+        #[arg(short, long)]
+        interpreter: Option<String>,
+
+        /// Markdown notes
+        #[arg(long)]
+        notes: Option<String>,
+
+        /// Example output to add (repeatable)
+        #[arg(long)]
+        add_example: Vec<String>,
+    },
+    /// Run a bookmark, filling in its `{{placeholders}}`
+    Run {
+        name: String,
+
+        /// Placeholder value as key=value (repeatable); missing values are prompted for
+        #[arg(short, long = "arg", value_name = "KEY=VALUE")]
+        args: Vec<String>,
+
+        /// Edit the command before running it
+        #[arg(short, long)]
+        edit: bool,
+
+        /// Run without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Print the command instead of running it
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Search bookmarks
     Search {
+        /// Words, quoted phrases, `name:`/`cmd:`/`desc:`/`tag:` fields,
+        /// `/regex/` and `!negated` terms
         query: String,
+
+        /// Treat every term as a regular expression
+        #[arg(long)]
+        regex: bool,
+
+        /// Match by meaning instead of words, using the embeddings index
+        #[arg(long, conflicts_with = "regex")]
+        semantic: bool,
+
+        /// Maximum number of semantic matches
+        #[arg(short, long, default_value_t = 10, requires = "semantic")]
+        limit: usize,
+    },
+    /// Smart save: Save a command as bookmark with AI-generated metadata
+    Save {
+        /// History entry to bookmark (default: the last command)
+        id: Option<u64>,
+
+        /// Bookmark this command instead of a history entry
+        #[arg(short, long, conflicts_with = "id")]
+        command: Option<String>,
+
+        /// Save the generated metadata without review, renaming on conflict
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Import bookmarks from navi, pet or tldr cheat sheets
+    Import {
+        /// Cheat sheet format to read
+        #[arg(long, value_enum)]
+        from: CheatFormat,
+
+        /// File or directory to read (defaults to the tool's standard location)
+        #[arg(short, long)]
+        file: Option<std::path::PathBuf>,
+
+        /// What to do when a bookmark name is already taken
+        #[arg(long, value_enum, default_value = "skip")]
+        on_conflict: ConflictPolicy,
+
+        /// Extra tag for every imported bookmark (repeatable)
+        #[arg(short, long)]
+        tag: Vec<String>,
+
+        /// List what would be imported without saving
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print bookmarks as shell aliases and functions, for `eval "$(shai bookmark alias)"`
+    Alias {
+        /// Target shell (default: from $SHELL, else bash)
+        #[arg(short, long, value_enum)]
+        shell: Option<Shell>,
+
+        /// Only bookmarks with this tag or one of its `tag/...` children
+        /// (repeatable or comma-separated; all must match)
+        #[arg(short, long, value_delimiter = ',')]
+        tag: Vec<String>,
+
+        /// Match bookmarks with any of the tags instead of all of them
+        #[arg(long, requires = "tag")]
+        any: bool,
+    },
+    /// Export bookmarks to JSON Lines, CSV, Markdown or a shell script
+    Export {
+        #[arg(short, long, value_enum, default_value = "jsonl")]
+        format: ExportFormat,
+
+        /// Only bookmarks with this tag or one of its `tag/...` children
+        /// (repeatable or comma-separated; all must match)
+        #[arg(short, long, value_delimiter = ',')]
+        tag: Vec<String>,
+
+        /// Match bookmarks with any of the tags instead of all of them
+        #[arg(long, requires = "tag")]
+        any: bool,
+
+        /// Only bookmarks created on or after this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only bookmarks created on or before this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
     },
-    /// Smart save: Save last command as bookmark with AI-generated metadata
-    Save,
 }
 
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
+    shai::config::load_env_files();
 
     // Handle subcommands
     if let Some(command) = cli.command {
         match command {
-            CliCommand::History { limit, search, clear } => {
-                return handle_history(limit, search, clear);
+            CliCommand::History { action: Some(action), .. } => {
+                return handle_history_action(action, cli.verbose).await;
+            }
+            CliCommand::History {
+                action: None, limit, search, regex, since, until, executed, failed, clear, here, model, edited, sort,
+            } => {
+                let filter = HistoryFilter {
+                    cwd: if here { shai::command::current_dir_string() } else { None },
+                    model,
+                    edited,
+                    since: since.map(|d| parse_date(&d, false)).transpose()?,
+                    until: until.map(|d| parse_date(&d, true)).transpose()?,
+                    executed,
+                    failed,
+                };
+                let query = search.map(|q| Query::parse(&q, regex)).transpose()?;
+                return handle_history(limit, query, clear, filter, sort);
             }
             CliCommand::Bookmark { action } => {
                 return handle_bookmark(action, cli.verbose).await;
             }
+            CliCommand::Cache { action } => {
+                return handle_cache(action);
+            }
         }
     }
 
     // Handle main command suggestion flow
     if let Some(description) = cli.description {
-        handle_suggest(&description, cli.verbose).await?;
+        handle_suggest(&description, cli.verbose, cli.no_cache).await?;
     } else {
         println!("Usage: shai \"<command description>\"");
         println!("       shai history [OPTIONS]");
         println!("       shai bookmark [SUBCOMMAND]");
+        println!("       shai cache [stats|clear]");
         println!("\nRun 'shai --help' for more information.");
     }
 
     Ok(())
 }
 
-async fn handle_suggest(user_input: &str, verbose: bool) -> Result<(), Box<dyn std::error::Error>> {
+fn create_client(config: &Config) -> Result<OpenAIClient, Box<dyn std::error::Error>> {
+    let client = OpenAIClient::builder()
+        .with_header("HTTP-Referer", "http://github.com/y1j2x34/shai")
+        .with_header("X-Title", "SHAI")
+        .with_endpoint(&config.endpoint)
+        .with_api_key(&config.api_key)
+        .build()?;
+    Ok(client)
+}
+
+/// Embeds with the provider when `SHAI_EMBEDDING_MODEL` is configured, with
+/// local hashing otherwise.
+fn embedder<'a>(config: Option<&'a Config>, client: Option<&'a OpenAIClient>) -> Embedder<'a> {
+    match (config, client) {
+        (Some(config), Some(client)) => match &config.embedding_model {
+            Some(model) => Embedder::Provider { client, model, redactor: &config.redactor },
+            None => Embedder::Hashing,
+        },
+        _ => Embedder::Hashing,
+    }
+}
+
+async fn handle_suggest(user_input: &str, verbose: bool, no_cache: bool) -> Result<(), Box<dyn std::error::Error>> {
     let config = Config::from_env()?;
     
     // Output verbose information if requested
@@ -115,66 +489,100 @@ async fn handle_suggest(user_input: &str, verbose: bool) -> Result<(), Box<dyn s
         println!("Endpoint: {}", config.endpoint);
         println!("Model: {}", config.model);
         println!("Suggestion count: {}", config.suggestion_count);
+        println!("Cache: {}", if config.cache && !no_cache { "enabled" } else { "disabled" });
         println!("User input: {}", user_input);
         println!("===================\n");
     }
     
     // Initialize OpenAI client
-    let client = OpenAIClient::builder()
-        .with_header("HTTP-Referer", "http://github.com/y1j2x34/shai")
-        .with_header("X-Title", "SHAI")
-        .with_endpoint(&config.endpoint)
-        .with_api_key(&config.api_key)
-        .build()?;
+    let client = create_client(&config)?;
 
-    let mut suggestions: Vec<Suggestion> = Vec::new();
-    
-    for i in 0..config.suggestion_count {
-        if verbose {
-            println!("Requesting suggestion {} of {}...", i + 1, config.suggestion_count);
-        }
-        
-        let mut retry_count = 3;
-        while retry_count > 0 {
-            let result = get_command_suggestion(&client, &config.model, user_input).await?;
-            
-            if verbose {
-                println!("Raw AI response: {}", result);
-            }
-            
-            let result = result.trim().trim_matches(&['`', '\n', '\r']).to_string();
-            let command = result.replace("command: ", "").trim_start().to_string();
-            
-            if command.is_empty() {
-                println!("Invalid command: {}", command);
-                retry_count -= 1;
-                if verbose {
-                    println!("Retrying... ({} attempts left)", retry_count);
-                }
-            } else {
-                let suggestion = Suggestion::new(command);
-                suggestions.push(suggestion);
+    // Similar past requests become few-shot examples and top suggestions
+    let history = History::new()?;
+    let similar = history.similar(user_input, config.history_examples.max(config.suggestion_count.max(0) as usize))?;
+    let examples: Vec<(String, String)> = similar
+        .iter()
+        .take(config.history_examples)
+        .map(|c| (c.description.clone(), c.command.clone()))
+        .collect();
+    if verbose && !similar.is_empty() {
+        println!("Found {} similar command(s) in history\n", similar.len());
+    }
+
+    // Bookmarks close in meaning are offered first; failing to find them
+    // must not stand in the way of suggestions
+    let matching_bookmarks: Vec<BookmarkItem> = if config.bookmark_suggestions {
+        let search = async {
+            EmbeddingIndex::new()?
+                .search(Bookmark::new()?.list(&TagFilter::default())?, user_input, &embedder(Some(&config), Some(&client)), config.suggestion_count.max(1) as usize)
+                .await
+        };
+        match search.await {
+            // Scripts need their interpreter, so only command lines are offered
+            Ok(matches) => matches.into_iter().map(|(item, _)| item).filter(|item| !item.is_script()).collect(),
+            Err(e) => {
                 if verbose {
-                    println!("✓ Suggestion generated successfully\n");
+                    println!("Bookmark search failed: {}\n", e);
                 }
-                retry_count = 0;
+                Vec::new()
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    // Bookmarks and past commands come first; the model is only asked when
+    // there are none, or when none of them fits
+    let mut offered: Vec<Suggestion> = matching_bookmarks
+        .iter()
+        .map(|item| Suggestion::new(item.command.clone()).with_origin(&format!("bookmark {}", item.name)))
+        .collect();
+    if config.history_suggestions {
+        for cmd in &similar {
+            if !offered.iter().any(|s| s.command == cmd.command) {
+                offered.push(Suggestion::new(cmd.command.clone()).with_origin("from history"));
             }
         }
     }
-    
-    if suggestions.is_empty() {
-        println!("Failed to generate command suggestions.");
-        return Ok(());
+
+    let mut shown = offered.clone();
+    let mut selection = None;
+    if !offered.is_empty() {
+        let mut choices = offered.clone();
+        choices.push(Suggestion::new("Ask the model for suggestions".to_string()));
+        let choice = Select::new(&format!("Suggested commands for: {}", user_input), choices).raw_prompt()?;
+        if choice.index < offered.len() {
+            selection = Some(choice.value);
+        }
     }
 
-    let selection = Select::new(
-        &format!("Suggested commands for: {}", user_input),
-        suggestions.clone(),
-    ).prompt()?;
-    
+    let selection = match selection {
+        Some(selection) => selection,
+        None => {
+            let cache = if config.cache && !no_cache { Some(ResponseCache::new()?.with_verbose(verbose)) } else { None };
+            let mut suggestions = model_suggestions(&config, &client, user_input, &examples, cache.as_ref(), verbose).await?;
+            suggestions.retain(|s| !offered.iter().any(|o| o.command == s.command));
+            if suggestions.is_empty() {
+                println!("Failed to generate command suggestions.");
+                return Ok(());
+            }
+            shown.extend(suggestions.iter().cloned());
+            Select::new(&format!("Suggested commands for: {}", user_input), suggestions).prompt()?
+        }
+    };
+
+    // A chosen bookmark has its parameters filled in before editing
+    let chosen_bookmark = matching_bookmarks
+        .iter()
+        .find(|item| selection.origin.as_deref() == Some(format!("bookmark {}", item.name).as_str()));
+    let proposed = match chosen_bookmark {
+        Some(item) => fill_placeholders(item, &[])?,
+        None => selection.command.clone(),
+    };
+
     let command = Text::new("")
         .with_help_message("Type to modify the suggested command, or press Enter to execute")
-        .with_initial_value(&selection.command)
+        .with_initial_value(&proposed)
         .prompt()?;
     
     let final_suggestion = Suggestion::new(command.clone());
@@ -186,9 +594,22 @@ async fn handle_suggest(user_input: &str, verbose: bool) -> Result<(), Box<dyn s
     }
     
     // Save to history before execution
-    let history = History::new();
-    let history_entry = Command::new(user_input.to_string(), command);
-    history.add(history_entry)?;
+    let mut history_entry = Command::new(user_input.to_string(), command.clone())
+        .with_environment()
+        .with_suggestions(shown.iter().map(|s| s.command.clone()).collect())
+        .with_edited(command != proposed);
+    // Only the model's own suggestions are attributed to it
+    if selection.origin.is_none() {
+        history_entry = history_entry.with_model(&config.model, &config.endpoint);
+    }
+    if let Some(item) = chosen_bookmark {
+        history_entry.bookmark = Some(item.name.clone());
+        // Shared bookmarks are read-only and have no usage to record
+        if item.source.is_none() {
+            Bookmark::personal()?.record_use(&item.name)?;
+        }
+    }
+    let id = history.add(history_entry)?;
     
     if verbose {
         println!("✓ Command saved to history");
@@ -196,13 +617,76 @@ async fn handle_suggest(user_input: &str, verbose: bool) -> Result<(), Box<dyn s
     }
     
     // Execute the command
-    final_suggestion.execute()?;
+    run_and_record(&history, id, || final_suggestion.run())
+}
+
+/// Asks the model for `config.suggestion_count` suggestions, retrying
+/// responses without a command.
+async fn model_suggestions(
+    config: &Config,
+    client: &OpenAIClient,
+    user_input: &str,
+    examples: &[(String, String)],
+    cache: Option<&ResponseCache>,
+    verbose: bool,
+) -> Result<Vec<Suggestion>, Box<dyn std::error::Error>> {
+    let mut suggestions: Vec<Suggestion> = Vec::new();
     
+    for i in 0..config.suggestion_count {
+        if verbose {
+            println!("Requesting suggestion {} of {}...", i + 1, config.suggestion_count);
+        }
+        
+        let mut retry_count = 3;
+        while retry_count > 0 {
+            let result = get_command_suggestion(
+                client, &config.model, &config.redactor, user_input, examples, cache, i as usize,
+            ).await?;
+            
+            if verbose {
+                println!("Raw AI response: {}", result);
+            }
+            
+            if let Some(command) = parse_command(&result) {
+                let suggestion = Suggestion::new(command);
+                suggestions.push(suggestion);
+                if verbose {
+                    println!("✓ Suggestion generated successfully\n");
+                }
+                retry_count = 0;
+            } else {
+                println!("Invalid command: {}", result.trim());
+                retry_count -= 1;
+                if verbose {
+                    println!("Retrying... ({} attempts left)", retry_count);
+                }
+            }
+        }
+    }
+
+    Ok(suggestions)
+}
+
+/// Runs the command through `run` and stores its exit code on history entry `id`.
+fn run_and_record(
+    history: &History,
+    id: u64,
+    run: impl FnOnce() -> Result<i32, Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let code = run()?;
+    history.update(id, |entry| {
+        entry.executed = true;
+        entry.exit_code = Some(code);
+    })?;
+
+    if code != 0 {
+        return Err(format!("Command exited with status {}", code).into());
+    }
     Ok(())
 }
 
-fn handle_history(limit: usize, search: Option<String>, clear: bool) -> Result<(), Box<dyn std::error::Error>> {
-    let history = History::new();
+fn handle_history(limit: usize, search: Option<Query>, clear: bool, filter: HistoryFilter, sort: HistorySort) -> Result<(), Box<dyn std::error::Error>> {
+    let history = History::new()?;
     
     if clear {
         history.clear()?;
@@ -210,102 +694,456 @@ fn handle_history(limit: usize, search: Option<String>, clear: bool) -> Result<(
         return Ok(());
     }
     
-    let commands = if let Some(query) = search {
-        history.search(&query)?
-    } else {
-        history.list(Some(limit))?
+    // Search shows every match, plain listing is limited
+    let limit = if search.is_some() { None } else { Some(limit) };
+    let mut commands = match search {
+        Some(query) => history.query(&query, sort)?,
+        None => history.list_by(sort, None)?,
     };
+    commands.retain(|cmd| filter.matches(cmd));
+    if let Some(n) = limit {
+        commands.truncate(n);
+    }
     
     if commands.is_empty() {
         println!("No history found.");
         return Ok(());
     }
     
-    println!("\n{:<20} {:<40} {}", "Time", "Description", "Command");
-    println!("{}", "-".repeat(100));
+    println!("\n{:<6} {:<20} {:<40} Command", "ID", "Time", "Description");
+    println!("{}", "-".repeat(106));
     
     for cmd in commands {
-        let datetime = chrono::DateTime::from_timestamp(cmd.timestamp, 0)
+        let datetime = chrono::DateTime::from_timestamp(cmd.last_used(), 0)
             .unwrap_or_default()
             .format("%Y-%m-%d %H:%M:%S");
         
-        let desc = if cmd.description.len() > 37 {
-            format!("{}...", &cmd.description[..37])
+        let desc = if cmd.description.is_empty() {
+            // Imported shell history has no description, show where it came from
+            cmd.source.as_ref().map(|s| format!("[{}]", s)).unwrap_or_default()
         } else {
-            cmd.description.clone()
+            truncate(&cmd.description, 40)
         };
         
-        let command_display = if cmd.command.len() > 37 {
-            format!("{}...", &cmd.command[..37])
+        let command_display = truncate(&cmd.command, 40);
+        let command_display = if cmd.count > 1 {
+            format!("{}  (×{})", command_display, cmd.count)
         } else {
-            cmd.command.clone()
+            command_display
         };
         
-        println!("{:<20} {:<40} {}", datetime, desc, command_display);
+        println!("{:<6} {:<20} {:<40} {}", cmd.id, datetime, desc, command_display);
     }
     
     Ok(())
 }
 
+/// History entry as shown in the fuzzy picker.
+struct HistoryChoice(Command);
+
+impl std::fmt::Display for HistoryChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let datetime = chrono::DateTime::from_timestamp(self.0.timestamp, 0)
+            .unwrap_or_default()
+            .format("%Y-%m-%d %H:%M");
+        let star = if self.0.starred { "★ " } else { "" };
+        write!(f, "{}{}  {}  →  {}", star, datetime, self.0.description, self.0.command)
+    }
+}
+
+async fn handle_history_action(action: HistoryAction, verbose: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let history = History::new()?;
+
+    match action {
+        HistoryAction::Pick => {
+            let commands = history.list(None)?;
+            if commands.is_empty() {
+                println!("No history found.");
+                return Ok(());
+            }
+
+            let choices: Vec<HistoryChoice> = commands.into_iter().map(HistoryChoice).collect();
+            let HistoryChoice(entry) = Select::new("Pick a command (type to filter):", choices)
+                .with_page_size(15)
+                .prompt()?;
+
+            let star_action = if entry.starred { "Unstar" } else { "Star" };
+            let actions = vec!["Run", "Edit then run", "Copy", "Bookmark", "Explain", star_action];
+            let choice = Select::new(&format!("What to do with: {}", entry.command), actions).prompt()?;
+
+            match choice {
+                "Run" | "Edit then run" => {
+                    let command = if choice == "Edit then run" {
+                        Text::new("")
+                            .with_help_message("Modify the command, then press Enter to execute")
+                            .with_initial_value(&entry.command)
+                            .prompt()?
+                    } else {
+                        entry.command.clone()
+                    };
+
+                    let history_entry = Command::new(entry.description.clone(), command.clone())
+                        .with_environment()
+                        .with_edited(command != entry.command);
+                    let id = history.add(history_entry)?;
+
+                    if verbose {
+                        println!("✓ Command saved to history");
+                        println!("Executing command...\n");
+                    }
+                    run_and_record(&history, id, || Suggestion::new(command).run())?;
+                }
+                "Copy" => {
+                    copy_to_clipboard(&entry.command)?;
+                    println!("✓ Copied to clipboard: {}", entry.command);
+                }
+                "Bookmark" => {
+                    let bookmark = Bookmark::new()?;
+                    save_bookmark(&bookmark, &entry.command, false, verbose).await?;
+                }
+                "Star" | "Unstar" => {
+                    history.update(entry.id, |e| e.starred = !entry.starred)?;
+                    println!("✓ {} entry: {}", if entry.starred { "Unstarred" } else { "Starred" }, entry.command);
+                }
+                _ => {
+                    let config = Config::from_env()?;
+                    let client = create_client(&config)?;
+                    let explanation = explain_command(&client, &config.model, &config.redactor, &entry.command).await?;
+                    println!("\n{}\n", explanation);
+
+                    if Confirm::new("Run this command now?").with_default(false).prompt()? {
+                        let id = history.add(Command::new(entry.description.clone(), entry.command.clone()).with_environment())?;
+                        run_and_record(&history, id, || Suggestion::new(entry.command).run())?;
+                    }
+                }
+            }
+        }
+        HistoryAction::Stats { top, json } => {
+            let commands = history.list(None)?;
+            let stats = HistoryStats::from_commands(&commands, top);
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            } else {
+                print_stats(&stats);
+            }
+        }
+        HistoryAction::Import { from, file } => {
+            let path = match file {
+                Some(path) => path,
+                None => from.default_history_path()?,
+            };
+            let commands = from.read(&path)?;
+            let total = commands.len();
+
+            if verbose {
+                println!("Read {} commands from {}", total, path.display());
+            }
+
+            let added = history.import(commands)?;
+            println!("✓ Imported {} of {} {} history entries", added, total, from.name());
+        }
+        HistoryAction::Export { format, since, until, tag, any, output } => {
+            let filter = HistoryFilter {
+                since: since.map(|d| parse_date(&d, false)).transpose()?,
+                until: until.map(|d| parse_date(&d, true)).transpose()?,
+                ..Default::default()
+            };
+            // Export oldest first so scripts replay in order
+            let mut commands = history.filter(&filter, None)?;
+            commands.reverse();
+
+            if !tag.is_empty() {
+                let tagged = Bookmark::new()?.list(&TagFilter { tags: tag, any })?;
+                commands.retain(|cmd| {
+                    tagged.iter().any(|item| cmd.bookmark.as_ref() == Some(&item.name) || cmd.command == item.command)
+                });
+            }
+
+            write_export(&export_history(&commands, format)?, output)?;
+        }
+        HistoryAction::Rm { ids, pattern, yes } => {
+            if let Some(pattern) = pattern {
+                let matched = history.find_matching(&pattern)?;
+                if matched.is_empty() {
+                    println!("No history entries match '{}'", pattern);
+                    return Ok(());
+                }
+
+                for cmd in &matched {
+                    println!("{:<6} {}", cmd.id, cmd.command);
+                }
+                let prompt = format!("Remove {} entries?", matched.len());
+                if !yes && !Confirm::new(&prompt).with_default(false).prompt()? {
+                    return Ok(());
+                }
+
+                let removed = history.remove_matching(&pattern)?;
+                println!("✓ Removed {} entries", removed.len());
+            } else {
+                for id in ids {
+                    let removed = history.remove(id)?;
+                    println!("✓ Removed entry {}: {}", id, removed.command);
+                }
+            }
+        }
+        HistoryAction::Edit { id, command, description } => {
+            let entry = history.get(id)?.ok_or_else(|| format!("History entry {} not found", id))?;
+
+            let (command, description) = if command.is_none() && description.is_none() {
+                let command = Text::new("Command:").with_initial_value(&entry.command).prompt()?;
+                let description = Text::new("Description:").with_initial_value(&entry.description).prompt()?;
+                (command, description)
+            } else {
+                (command.unwrap_or(entry.command), description.unwrap_or(entry.description))
+            };
+
+            history.update(id, |e| {
+                e.command = command;
+                e.description = description;
+            })?;
+            println!("✓ Updated entry {}", id);
+        }
+        HistoryAction::Prune { dry_run } => {
+            let pruned = history.prune(dry_run)?;
+            if pruned.is_empty() {
+                println!("Nothing to prune.");
+                return Ok(());
+            }
+
+            for cmd in &pruned {
+                let datetime = chrono::DateTime::from_timestamp(cmd.timestamp, 0)
+                    .unwrap_or_default()
+                    .format("%Y-%m-%d %H:%M:%S");
+                println!("{:<6} {:<20} {}", cmd.id, datetime, cmd.command);
+            }
+
+            if dry_run {
+                println!("\n{} entries would be pruned (dry run).", pruned.len());
+            } else {
+                println!("\n✓ Pruned {} entries.", pruned.len());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_export(content: &str, output: Option<std::path::PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    match output {
+        Some(path) => {
+            std::fs::write(&path, content)?;
+            eprintln!("✓ Exported to {}", path.display());
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+fn handle_cache(action: CacheAction) -> Result<(), Box<dyn std::error::Error>> {
+    let cache = ResponseCache::new()?;
+
+    match action {
+        CacheAction::Stats => {
+            let stats = cache.stats()?;
+            let format_time = |timestamp: Option<i64>| {
+                timestamp
+                    .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|| "-".to_string())
+            };
+            println!("Cache file: {}", stats.path.display());
+            println!("Entries:    {}  ({} responses, {} expired)", stats.entries, stats.responses, stats.expired);
+            println!("Oldest:     {}", format_time(stats.oldest));
+            println!("Newest:     {}", format_time(stats.newest));
+        }
+        CacheAction::Clear => {
+            let removed = cache.clear()?;
+            println!("✓ Removed {} cached response set(s)", removed);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_stats(stats: &HistoryStats) {
+    println!("\nTotal entries: {}  (executed: {})", stats.total, stats.executed);
+    if let Some(rate) = stats.suggestion_acceptance_rate {
+        println!("Suggestion acceptance rate: {:.1}%", rate * 100.0);
+    }
+
+    let sections = [
+        ("Most used commands", &stats.top_commands),
+        ("Most used programs", &stats.top_programs),
+        ("Busiest hours", &stats.busiest_hours),
+    ];
+    for (title, entries) in sections {
+        if entries.is_empty() {
+            continue;
+        }
+        println!("\n{}", title);
+        println!("{}", "-".repeat(60));
+        for entry in entries {
+            println!("{:<52} {:>7}", truncate(&entry.name, 50), entry.count);
+        }
+    }
+
+    let rate_sections = [
+        ("Edit rate by model", "Edited", &stats.edit_rate_by_model),
+        ("Failure rate by program", "Failed", &stats.failure_rate_by_program),
+    ];
+    for (title, label, entries) in rate_sections {
+        if entries.is_empty() {
+            continue;
+        }
+        println!("\n{}", title);
+        println!("{:<40} {:>7} {:>7} {:>7}", "Name", "Total", label, "Rate");
+        println!("{}", "-".repeat(64));
+        for entry in entries {
+            println!("{:<40} {:>7} {:>7} {:>6.1}%", truncate(&entry.name, 38), entry.total, entry.matched, entry.rate * 100.0);
+        }
+    }
+}
+
+/// Shortens `text` to at most `max` characters, ending in "..." when cut.
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() > max {
+        format!("{}...", text.chars().take(max.saturating_sub(3)).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
 async fn handle_bookmark(action: BookmarkAction, verbose: bool) -> Result<(), Box<dyn std::error::Error>> {
-    let bookmark = Bookmark::new();
+    let bookmark = Bookmark::new()?;
     
     match action {
-        BookmarkAction::Add { name, command, description, tags } => {
+        BookmarkAction::Add { name, command, file, description, tags, interpreter, notes, examples } => {
+            let (command, interpreter) = match file {
+                Some(file) => {
+                    let script = std::fs::read_to_string(&file)
+                        .map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+                    let interpreter = interpreter.or_else(|| shebang_interpreter(&script));
+                    (script, interpreter)
+                }
+                None => (command.unwrap_or_default(), interpreter),
+            };
             let item = BookmarkItem {
                 name: name.clone(),
                 command,
                 description: description.unwrap_or_default(),
-                tags,
+                tags: normalize_tags(&tags)?,
                 created_at: chrono::Utc::now().timestamp(),
+                interpreter: interpreter.filter(|i| !i.trim().is_empty()),
+                notes: notes.unwrap_or_default(),
+                examples,
+                ..Default::default()
             };
             bookmark.add(item)?;
             println!("Bookmark '{}' added successfully!", name);
         }
-        BookmarkAction::List { tag } => {
-            let items = bookmark.list(tag)?;
+        BookmarkAction::List { tag, any } => {
+            let items = bookmark.list(&TagFilter { tags: tag, any })?;
             if items.is_empty() {
                 println!("No bookmarks found.");
                 return Ok(());
             }
             
-            println!("\n{:<20} {:<40} {}", "Name", "Command", "Tags");
+            println!("\n{:<20} {:<40} Tags", "Name", "Command");
             println!("{}", "-".repeat(100));
             
             for item in items {
-                let name_display = if item.name.len() > 17 {
-                    format!("{}...", &item.name[..17])
-                } else {
-                    item.name.clone()
-                };
+                let name_display = truncate(&item.name, 20);
                 
-                let command_display = if item.command.len() > 37 {
-                    format!("{}...", &item.command[..37])
-                } else {
-                    item.command.clone()
-                };
+                let command_display = truncate(&item.command, 40);
                 
                 let tags_display = item.tags.join(", ");
-                let tags_display = if tags_display.len() > 37 {
-                    format!("{}...", &tags_display[..37])
-                } else {
-                    tags_display
-                };
+                let tags_display = truncate(&tags_display, 40);
                 
                 println!("{:<20} {:<40} {}", name_display, command_display, tags_display);
             }
+
+            if !bookmark.sources().is_empty() {
+                println!("\nShared collections (read-only):");
+                for source in bookmark.sources() {
+                    println!("  {:<18} {}", format!("{}/", source.namespace), source.path.display());
+                }
+            }
         }
+        BookmarkAction::Tags { action } => match action.unwrap_or(TagAction::List) {
+            TagAction::List => {
+                let tags = bookmark.tags()?;
+                if tags.is_empty() {
+                    println!("No tags found.");
+                    return Ok(());
+                }
+
+                println!("\n{:<40} Bookmarks", "Tag");
+                println!("{}", "-".repeat(50));
+                for (tag, count) in tags {
+                    // Children are indented under their parent
+                    let depth = tag.matches('/').count();
+                    println!("{:<40} {}", format!("{}{}", "  ".repeat(depth), tag), count);
+                }
+            }
+            TagAction::Rename { from, to } => {
+                let changed = bookmark.rename_tag(&from, &to)?;
+                println!("Renamed tag '{}' to '{}' on {} bookmark(s)", from, to, changed);
+            }
+            TagAction::Merge { tags, into } => {
+                let changed = bookmark.merge_tags(&tags, &into)?;
+                println!("Merged {} into '{}' on {} bookmark(s)", tags.join(", "), into, changed);
+            }
+        },
         BookmarkAction::Get { name } => {
             if let Some(item) = bookmark.get(&name)? {
                 println!("\nBookmark: {}", item.name);
-                println!("Command: {}", item.command);
+                match &item.interpreter {
+                    Some(interpreter) => println!("Script ({}):\n{}", interpreter, indent(&item.command, 4)),
+                    None if item.command.contains('\n') => println!("Command:\n{}", indent(&item.command, 4)),
+                    None => println!("Command: {}", item.command),
+                }
                 println!("Description: {}", item.description);
                 println!("Tags: {}", item.tags.join(", "));
+                if let Some(source) = &item.source {
+                    println!("Source: {} (read-only)", source);
+                }
+                let placeholders = item.placeholders().unwrap_or_default();
+                if !placeholders.is_empty() {
+                    let names: Vec<String> = placeholders.iter().map(describe_placeholder).collect();
+                    println!("Parameters: {}", names.join(", "));
+                }
                 
-                let datetime = chrono::DateTime::from_timestamp(item.created_at, 0)
-                    .unwrap_or_default()
-                    .format("%Y-%m-%d %H:%M:%S");
-                println!("Created: {}", datetime);
+                // Shared collections usually carry no timestamps
+                if item.created_at > 0 {
+                    let datetime = chrono::DateTime::from_timestamp(item.created_at, 0)
+                        .unwrap_or_default()
+                        .format("%Y-%m-%d %H:%M:%S");
+                    println!("Created: {}", datetime);
+                }
+                if let Some(updated_at) = item.updated_at {
+                    let datetime = chrono::DateTime::from_timestamp(updated_at, 0)
+                        .unwrap_or_default()
+                        .format("%Y-%m-%d %H:%M:%S");
+                    println!("Updated: {}", datetime);
+                }
+                if let Some(last_used) = item.last_used {
+                    let datetime = chrono::DateTime::from_timestamp(last_used, 0)
+                        .unwrap_or_default()
+                        .format("%Y-%m-%d %H:%M:%S");
+                    println!("Used: {} time(s), last {}", item.use_count, datetime);
+                }
+                if !item.notes.trim().is_empty() {
+                    println!("\nNotes:\n{}", indent(shai::markdown::render(&item.notes).trim_end(), 2));
+                }
+                for (i, example) in item.examples.iter().enumerate() {
+                    if i == 0 {
+                        println!("\nExample output:");
+                    } else {
+                        println!();
+                    }
+                    println!("{}", indent(example.trim_end(), 4));
+                }
             } else {
                 println!("Bookmark '{}' not found", name);
             }
@@ -314,93 +1152,439 @@ async fn handle_bookmark(action: BookmarkAction, verbose: bool) -> Result<(), Bo
             bookmark.remove(&name)?;
             println!("Bookmark '{}' removed", name);
         }
-        BookmarkAction::Search { query } => {
-            let items = bookmark.search(&query)?;
+        BookmarkAction::Edit { name, rename, command, description, add_tag, remove_tag, interpreter, notes, add_example } => {
+            let item = bookmark.get(&name)?.ok_or_else(|| format!("Bookmark '{}' not found", name))?;
+            let use_editor = rename.is_none() && command.is_none() && description.is_none()
+                && add_tag.is_empty() && remove_tag.is_empty()
+                && interpreter.is_none() && notes.is_none() && add_example.is_empty();
+
+            let updated = if use_editor {
+                let original = BookmarkEdit::from_item(&item);
+                let edited = edit_bookmark_in_editor(&original)?;
+                if edited == original {
+                    println!("No changes");
+                    return Ok(());
+                }
+                bookmark.update(&name, |item| edited.apply(item))?
+            } else {
+                let add_tag = normalize_tags(&add_tag)?;
+                let remove_tag = normalize_tags(&remove_tag)?;
+                bookmark.update(&name, |item| {
+                    if let Some(rename) = rename {
+                        item.name = rename;
+                    }
+                    if let Some(command) = command {
+                        item.command = command;
+                    }
+                    if let Some(description) = description {
+                        item.description = description;
+                    }
+                    item.tags.retain(|t| !remove_tag.contains(t));
+                    for tag in add_tag {
+                        if !item.tags.contains(&tag) {
+                            item.tags.push(tag);
+                        }
+                    }
+                    if let Some(interpreter) = interpreter {
+                        item.interpreter = Some(interpreter).filter(|i| !i.trim().is_empty());
+                    }
+                    if let Some(notes) = notes {
+                        item.notes = notes;
+                    }
+                    item.examples.extend(add_example);
+                })?
+            };
+
+            if updated.name != name {
+                println!("Bookmark '{}' renamed to '{}'", name, updated.name);
+            } else {
+                println!("Bookmark '{}' updated", name);
+            }
+        }
+        BookmarkAction::Run { name, args, edit, yes, dry_run } => {
+            let item = bookmark.get(&name)?.ok_or_else(|| format!("Bookmark '{}' not found", name))?;
+            let rendered = fill_placeholders(&item, &args)?;
+
+            if dry_run {
+                println!("{}", rendered);
+                return Ok(());
+            }
+
+            let command = if edit && item.is_script() {
+                edit_text_in_editor(&rendered)?
+            } else if edit {
+                Text::new("")
+                    .with_help_message("Modify the command, or press Enter to run it")
+                    .with_initial_value(&rendered)
+                    .prompt()?
+            } else {
+                rendered.clone()
+            };
+
+            if !yes {
+                match &item.interpreter {
+                    Some(interpreter) => println!("Script ({}):\n{}", interpreter, indent(&command, 4)),
+                    None => println!("Command: {}", command),
+                }
+                if !Confirm::new("Run this command?").with_default(true).prompt()? {
+                    println!("Cancelled");
+                    return Ok(());
+                }
+            }
+
+            let history = History::new()?;
+            let description = if item.description.is_empty() { item.name.clone() } else { item.description.clone() };
+            let mut entry = Command::new(description, command.clone())
+                .with_environment()
+                .with_edited(command != rendered);
+            entry.bookmark = Some(item.name.clone());
+            let id = history.add(entry)?;
+            bookmark.record_use(&item.name)?;
+
+            if verbose {
+                println!("✓ Command saved to history");
+                println!("Executing command...\n");
+            }
+            match &item.interpreter {
+                Some(interpreter) => run_and_record(&history, id, || run_script(&command, interpreter))?,
+                None => run_and_record(&history, id, || Suggestion::new(command).run())?,
+            }
+        }
+        BookmarkAction::Search { query, regex, semantic, limit } => {
+            let items = if semantic {
+                // The provider is only needed when it computes the embeddings
+                let config = std::env::var_os("SHAI_EMBEDDING_MODEL").map(|_| Config::from_env()).transpose()?;
+                let client = config.as_ref().map(create_client).transpose()?;
+                let matches = EmbeddingIndex::new()?
+                    .search(bookmark.list(&TagFilter::default())?, &query, &embedder(config.as_ref(), client.as_ref()), limit)
+                    .await?;
+                if verbose {
+                    for (item, score) in &matches {
+                        println!("{:.2}  {}", score, item.name);
+                    }
+                }
+                matches.into_iter().map(|(item, _)| item).collect()
+            } else {
+                bookmark.query(&Query::parse(&query, regex)?)?
+            };
             if items.is_empty() {
                 println!("No bookmarks found matching '{}'", query);
                 return Ok(());
             }
             
-            println!("\n{:<20} {:<40} {}", "Name", "Command", "Tags");
+            println!("\n{:<20} {:<40} Tags", "Name", "Command");
             println!("{}", "-".repeat(100));
             
             for item in items {
-                let name_display = if item.name.len() > 17 {
-                    format!("{}...", &item.name[..17])
-                } else {
-                    item.name.clone()
-                };
+                let name_display = truncate(&item.name, 20);
                 
-                let command_display = if item.command.len() > 37 {
-                    format!("{}...", &item.command[..37])
-                } else {
-                    item.command.clone()
-                };
+                let command_display = truncate(&item.command, 40);
                 
                 let tags_display = item.tags.join(", ");
                 
                 println!("{:<20} {:<40} {}", name_display, command_display, tags_display);
             }
         }
-        BookmarkAction::Save => {
-            // Get last command from history
-            let history = History::new();
-            let commands = history.list(Some(1))?;
-            
-            if commands.is_empty() {
-                println!("No command history found. Run a command first.");
-                return Ok(());
-            }
-            
-            let last_command = &commands[0];
-            
-            if verbose {
-                println!("=== Verbose Mode ===");
-                println!("Command to bookmark: {}", last_command.command);
-                println!("Generating bookmark metadata with AI...");
-                println!("===================\n");
-            } else {
-                println!("Generating bookmark metadata for: {}", last_command.command);
+        BookmarkAction::Save { id, command, yes } => {
+            let command = match (id, command) {
+                (_, Some(command)) => command,
+                (Some(id), None) => History::new()?
+                    .get(id)?
+                    .ok_or_else(|| format!("History entry {} not found", id))?
+                    .command,
+                (None, None) => {
+                    // Default to the last command from history
+                    let history = History::new()?;
+                    let commands = history.list(Some(1))?;
+
+                    if commands.is_empty() {
+                        println!("No command history found. Run a command first.");
+                        return Ok(());
+                    }
+                    commands[0].command.clone()
+                }
+            };
+
+            save_bookmark(&bookmark, &command, yes, verbose).await?;
+        }
+        BookmarkAction::Import { from, file, on_conflict, tag, dry_run } => {
+            let path = match file {
+                Some(path) => path,
+                None => from.default_path()?,
+            };
+            let tag = normalize_tags(&tag)?;
+            let mut items = from.read(&path)?;
+            for item in &mut items {
+                for extra in &tag {
+                    if !item.tags.contains(extra) {
+                        item.tags.push(extra.clone());
+                    }
+                }
             }
-            
-            // Load config and initialize AI client
-            let config = Config::from_env()?;
-            let client = OpenAIClient::builder()
-                .with_header("HTTP-Referer", "http://github.com/y1j2x34/shai")
-                .with_header("X-Title", "SHAI")
-                .with_endpoint(&config.endpoint)
-                .with_api_key(&config.api_key)
-                .build()?;
-            
-            // Generate bookmark metadata with AI
-            let metadata = generate_bookmark_info(&client, &config.model, &last_command.command).await?;
-            
-            if verbose {
-                println!("AI generated metadata:");
-                println!("  Name: {}", metadata.name);
-                println!("  Description: {}", metadata.description);
-                println!("  Tags: {:?}\n", metadata.tags);
+
+            if verbose || dry_run {
+                println!("Read {} bookmarks from {}", items.len(), path.display());
             }
-            
-            // Check if bookmark already exists
-            if let Some(_existing) = bookmark.get(&metadata.name)? {
-                println!("⚠ Bookmark '{}' already exists. Please use 'bookmark remove' first or choose a different name.", metadata.name);
+            if dry_run {
+                for item in &items {
+                    println!("  {:<30} {}", item.name, item.command.lines().next().unwrap_or_default());
+                }
                 return Ok(());
             }
-            
-            // Create and save the bookmark
-            let item = BookmarkItem {
-                name: metadata.name.clone(),
-                command: last_command.command.clone(),
-                description: metadata.description,
-                tags: metadata.tags,
-                created_at: chrono::Utc::now().timestamp(),
-            };
-            
-            bookmark.add(item)?;
-            println!("✓ Bookmark '{}' saved successfully!", metadata.name);
+
+            let total = items.len();
+            let summary = bookmark.import(items, on_conflict)?;
+            println!(
+                "✓ Imported {} of {} {} bookmarks ({} renamed, {} replaced, {} skipped)",
+                summary.added + summary.renamed + summary.replaced,
+                total,
+                from.name(),
+                summary.renamed,
+                summary.replaced,
+                summary.skipped
+            );
+        }
+        BookmarkAction::Alias { shell, tag, any } => {
+            let shell = shell.or_else(Shell::detect).unwrap_or(Shell::Bash);
+            print!("{}", export_aliases(&bookmark.list(&TagFilter { tags: tag, any })?, shell)?);
+        }
+        BookmarkAction::Export { format, tag, any, since, until, output } => {
+            let since = since.map(|d| parse_date(&d, false)).transpose()?;
+            let until = until.map(|d| parse_date(&d, true)).transpose()?;
+            let mut items = bookmark.list(&TagFilter { tags: tag, any })?;
+            items.retain(|item| {
+                since.is_none_or(|since| item.created_at >= since) && until.is_none_or(|until| item.created_at <= until)
+            });
+            write_export(&export_bookmarks(&items, format)?, output)?;
         }
     }
     
     Ok(())
 }
+
+/// Opens the bookmark as TOML in `$VISUAL`/`$EDITOR` and parses the result,
+/// offering to re-open the editor when it does not parse.
+fn edit_bookmark_in_editor(original: &BookmarkEdit) -> Result<BookmarkEdit, Box<dyn std::error::Error>> {
+    let file = editor_file(&original.to_toml()?, ".toml")?;
+    let path = file.path();
+
+    loop {
+        open_in_editor(path)?;
+
+        match BookmarkEdit::from_toml(&std::fs::read_to_string(path)?) {
+            Ok(edited) => return Ok(edited),
+            Err(e) => {
+                println!("{}", e);
+                if !Confirm::new("Edit again?").with_default(true).prompt()? {
+                    return Err("Edit cancelled".into());
+                }
+            }
+        }
+    }
+}
+
+/// Lets the user edit `text` in $VISUAL or $EDITOR and returns the result.
+fn edit_text_in_editor(text: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let file = editor_file(text, ".txt")?;
+    open_in_editor(file.path())?;
+    Ok(std::fs::read_to_string(file.path())?)
+}
+
+/// A new temporary file holding `text`, readable only by the user and
+/// removed when dropped.
+fn editor_file(text: &str, suffix: &str) -> Result<tempfile::NamedTempFile, Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    let mut file = tempfile::Builder::new().prefix("shai-").suffix(suffix).tempfile()?;
+    file.write_all(text.as_bytes())?;
+    file.flush()?;
+    Ok(file)
+}
+
+/// Opens `path` in $VISUAL or $EDITOR and waits for the editor to exit.
+fn open_in_editor(path: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad".to_string() } else { "vi".to_string() });
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().ok_or("Editor is empty: set $EDITOR")?;
+    let args: Vec<&str> = parts.collect();
+
+    let status = std::process::Command::new(program).args(&args).arg(path).status()
+        .map_err(|e| format!("Cannot start editor '{}': {}", editor, e))?;
+    if !status.success() {
+        return Err(format!("Editor exited with {}", status).into());
+    }
+    Ok(())
+}
+
+/// The program named by a script's `#!` line, without `/usr/bin/env`.
+fn shebang_interpreter(script: &str) -> Option<String> {
+    let line = script.lines().next()?.strip_prefix("#!")?.trim();
+    let line = line.strip_prefix("/usr/bin/env").map(str::trim).unwrap_or(line);
+    let line = line.strip_prefix("-S").map(str::trim).unwrap_or(line);
+    (!line.is_empty()).then(|| line.to_string())
+}
+
+/// Indents every line of `text` by `width` spaces.
+fn indent(text: &str, width: usize) -> String {
+    let pad = " ".repeat(width);
+    text.lines()
+        .map(|line| if line.is_empty() { String::new() } else { format!("{}{}", pad, line) })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders a bookmark's command from `--arg key=value` pairs, prompting for
+/// any placeholder without a value.
+fn fill_placeholders(item: &BookmarkItem, args: &[String]) -> Result<String, Box<dyn std::error::Error>> {
+    let placeholders = item.placeholders()?;
+    let mut values = shai::template::parse_args(args)?;
+
+    if let Some(unknown) = values.keys().find(|key| !placeholders.iter().any(|p| &p.name == *key)) {
+        return Err(format!("Bookmark '{}' has no parameter '{}'", item.name, unknown).into());
+    }
+
+    for placeholder in &placeholders {
+        if let Some(value) = values.get(&placeholder.name) {
+            placeholder.validate(value)?;
+        }
+    }
+
+    for placeholder in &placeholders {
+        if values.contains_key(&placeholder.name) {
+            continue;
+        }
+        let label = format!("{}:", placeholder.name);
+        let value = if placeholder.choices.is_empty() {
+            let mut prompt = Text::new(&label);
+            if let Some(default) = &placeholder.default {
+                prompt = prompt.with_default(default);
+            }
+            prompt.prompt()?
+        } else {
+            let start = placeholder.default
+                .as_ref()
+                .and_then(|d| placeholder.choices.iter().position(|c| c == d))
+                .unwrap_or(0);
+            Select::new(&label, placeholder.choices.clone())
+                .with_starting_cursor(start)
+                .prompt()?
+        };
+        values.insert(placeholder.name.clone(), value);
+    }
+
+    // Shell quoting would corrupt values in e.g. Python, so other
+    // interpreters get them as typed
+    match &item.interpreter {
+        Some(interpreter) if !is_shell(interpreter) => shai::template::render_raw(&item.command, &values),
+        _ => shai::template::render(&item.command, &values),
+    }
+}
+
+/// Whether `interpreter` is a POSIX-style shell, which expects quoted values.
+fn is_shell(interpreter: &str) -> bool {
+    let program = interpreter.split_whitespace().next().unwrap_or_default();
+    let program = program.rsplit(['/', '\\']).next().unwrap_or(program);
+    matches!(program, "sh" | "bash" | "zsh" | "dash" | "ksh")
+}
+
+fn describe_placeholder(placeholder: &Placeholder) -> String {
+    let mut description = placeholder.name.clone();
+    if !placeholder.choices.is_empty() {
+        description.push_str(&format!(" ({})", placeholder.choices.join("|")));
+    }
+    if let Some(default) = &placeholder.default {
+        description.push_str(&format!(" [default: {}]", default));
+    }
+    description
+}
+
+/// Generates bookmark metadata for `command` with AI, lets the user review
+/// it and saves it. With `yes`, the proposal is saved as-is and a name
+/// conflict is resolved with a numeric suffix.
+async fn save_bookmark(bookmark: &Bookmark, command: &str, yes: bool, verbose: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if verbose {
+        println!("=== Verbose Mode ===");
+        println!("Command to bookmark: {}", command);
+        println!("Generating bookmark metadata with AI...");
+        println!("===================\n");
+    } else {
+        println!("Generating bookmark metadata for: {}", command);
+    }
+    
+    // Load config and initialize AI client
+    let config = Config::from_env()?;
+    let client = create_client(&config)?;
+    
+    // Generate bookmark metadata with AI
+    let metadata = generate_bookmark_info(&client, &config.model, &config.redactor, command).await?;
+    
+    if verbose {
+        println!("AI generated metadata:");
+        println!("  Name: {}", metadata.name);
+        println!("  Description: {}", metadata.description);
+        println!("  Tags: {:?}\n", metadata.tags);
+    }
+
+    // Generated tags may contain spaces
+    let proposed_tags: Vec<String> = metadata.tags.iter().filter_map(|t| tag_from_text(t)).collect();
+    let proposed_tags = normalize_tags(&proposed_tags)?;
+    let (mut name, description, tags) = if yes {
+        (metadata.name, metadata.description, proposed_tags)
+    } else {
+        let name = Text::new("Name:").with_initial_value(&metadata.name).prompt()?;
+        let description = Text::new("Description:").with_initial_value(&metadata.description).prompt()?;
+        let split = |input: &str| -> Vec<String> {
+            input.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect()
+        };
+        let tags = Text::new("Tags:")
+            .with_help_message("Comma-separated")
+            .with_initial_value(&proposed_tags.join(", "))
+            .with_validator(move |input: &str| {
+                Ok(match normalize_tags(&split(input)) {
+                    Ok(_) => Validation::Valid,
+                    Err(e) => Validation::Invalid(e.to_string().into()),
+                })
+            })
+            .prompt()?;
+        (name.trim().to_string(), description, normalize_tags(&split(&tags))?)
+    };
+
+    // Resolve a clash with an existing bookmark
+    while bookmark.get(&name)?.is_some() {
+        let suggested = bookmark.unique_name(&name)?;
+        if yes {
+            name = suggested;
+            break;
+        }
+
+        let use_suggested = format!("Save as '{}'", suggested);
+        let options = vec![use_suggested.clone(), "Choose another name".to_string(), "Cancel".to_string()];
+        let choice = Select::new(&format!("Bookmark '{}' already exists", name), options).prompt()?;
+        if choice == use_suggested {
+            name = suggested;
+        } else if choice == "Cancel" {
+            println!("Cancelled");
+            return Ok(());
+        } else {
+            name = Text::new("Name:").with_initial_value(&suggested).prompt()?.trim().to_string();
+        }
+    }
+    
+    // Create and save the bookmark. Braces in the command are literal, not
+    // parameters
+    let item = BookmarkItem {
+        name: name.clone(),
+        command: shai::template::escape_literal(command),
+        description,
+        tags,
+        created_at: chrono::Utc::now().timestamp(),
+        ..Default::default()
+    };
+    
+    bookmark.add(item)?;
+    println!("✓ Bookmark '{}' saved successfully!", name);
+    
+    Ok(())
+}
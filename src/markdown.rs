@@ -0,0 +1,47 @@
+/// Renders markdown as plain terminal text: headings are underlined, list
+/// markers become bullets, code blocks are indented, and emphasis, inline
+/// code and link markup is reduced to its text.
+pub fn render(text: &str) -> String {
+    let mut out = String::new();
+    let mut in_code = false;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            out.push_str(&format!("    {}\n", line));
+            continue;
+        }
+
+        let indent = &line[..line.len() - trimmed.len()];
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+            let heading = inline(trimmed[level..].trim());
+            let rule = if level == 1 { '=' } else { '-' };
+            out.push_str(&format!("{}\n{}\n", heading, rule.to_string().repeat(heading.chars().count())));
+        } else if let Some(item) = ["- ", "* ", "+ "].iter().find_map(|m| trimmed.strip_prefix(m)) {
+            out.push_str(&format!("{}• {}\n", indent, inline(item)));
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            out.push_str(&format!("{}│ {}\n", indent, inline(quote.trim_start())));
+        } else {
+            out.push_str(&format!("{}{}\n", indent, inline(trimmed)));
+        }
+    }
+
+    out
+}
+
+/// Strips inline markup, keeping link targets after their text.
+fn inline(text: &str) -> String {
+    let link = regex::Regex::new(r"\[([^\]]*)\]\(([^)]*)\)").expect("valid regex");
+    let text = link.replace_all(text, "$1 ($2)");
+    let emphasis = regex::Regex::new(r"(\*\*|__)(.+?)(\*\*|__)|`([^`]*)`").expect("valid regex");
+    emphasis
+        .replace_all(&text, |caps: &regex::Captures| {
+            caps.get(2).or_else(|| caps.get(4)).map_or("", |m| m.as_str()).to_string()
+        })
+        .to_string()
+}
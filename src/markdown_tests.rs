@@ -0,0 +1,15 @@
+use crate::markdown::render;
+
+#[test]
+fn test_render_markdown() {
+    let notes = "# Cleanup\n\nRuns **weekly**, see [docs](https://example.com).\n\n- needs `docker`\n  * nested\n> careful\n\n```\ndocker ps -a\n```\n";
+    assert_eq!(
+        render(notes),
+        "Cleanup\n=======\n\nRuns weekly, see docs (https://example.com).\n\n• needs docker\n  • nested\n│ careful\n\n    docker ps -a\n"
+    );
+}
+
+#[test]
+fn test_render_keeps_plain_text() {
+    assert_eq!(render("2 * 3 = 6\n#hashtag"), "2 * 3 = 6\n#hashtag\n");
+}
@@ -0,0 +1,237 @@
+use regex::Regex;
+use crate::bookmark::BookmarkItem;
+use crate::command::Command;
+
+/// Fields a query term can be scoped to with `field:value`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Command,
+    Description,
+    Name,
+    Tag,
+    Cwd,
+    Model,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "cmd" | "command" => Some(Field::Command),
+            "desc" | "description" => Some(Field::Description),
+            "name" => Some(Field::Name),
+            "tag" | "tags" => Some(Field::Tag),
+            "cwd" | "dir" => Some(Field::Cwd),
+            "model" => Some(Field::Model),
+            _ => None,
+        }
+    }
+}
+
+/// Something a `Query` can be run against.
+pub trait Searchable {
+    /// Values of `field`; empty when the item has no such field.
+    fn field_values(&self, field: Field) -> Vec<&str>;
+
+    /// Fields searched by unscoped terms, with their ranking weights.
+    fn default_fields() -> &'static [(Field, u32)];
+}
+
+impl Searchable for Command {
+    fn field_values(&self, field: Field) -> Vec<&str> {
+        match field {
+            Field::Command => vec![self.command.as_str()],
+            Field::Description => vec![self.description.as_str()],
+            Field::Cwd => self.cwd.as_deref().into_iter().collect(),
+            Field::Model => self.model.as_deref().into_iter().collect(),
+            Field::Name | Field::Tag => Vec::new(),
+        }
+    }
+
+    fn default_fields() -> &'static [(Field, u32)] {
+        &[(Field::Command, 2), (Field::Description, 1)]
+    }
+}
+
+impl Searchable for BookmarkItem {
+    fn field_values(&self, field: Field) -> Vec<&str> {
+        match field {
+            Field::Command => vec![self.command.as_str()],
+            Field::Description => vec![self.description.as_str()],
+            Field::Name => vec![self.name.as_str()],
+            Field::Tag => self.tags.iter().map(String::as_str).collect(),
+            Field::Cwd | Field::Model => Vec::new(),
+        }
+    }
+
+    fn default_fields() -> &'static [(Field, u32)] {
+        &[(Field::Name, 3), (Field::Command, 2), (Field::Description, 1), (Field::Tag, 1)]
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    /// Lowercased substring
+    Text(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    /// Scores how well `value` matches: exact beats prefix beats substring.
+    fn score(&self, value: &str) -> u32 {
+        match self {
+            Matcher::Text(text) => {
+                let value = value.to_lowercase();
+                if value == *text {
+                    10
+                } else if value.starts_with(text.as_str()) {
+                    6
+                } else if value.contains(text.as_str()) {
+                    3
+                } else {
+                    0
+                }
+            }
+            Matcher::Regex(regex) => if regex.is_match(value) { 3 } else { 0 },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Term {
+    field: Option<Field>,
+    matcher: Matcher,
+    negated: bool,
+}
+
+/// A parsed search query.
+///
+/// Terms are separated by whitespace (use quotes to keep spaces) and must all
+/// match. A term may be scoped to a field (`cmd:docker`, `desc:"free space"`,
+/// `tag:k8s`), written as a regex (`/^git (push|pull)/`), or negated with a
+/// leading `!` (not `-`, which would clash with command flags). Text terms
+/// match case-insensitively.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+impl Query {
+    /// Parses `input`. With `regex` set, every term is treated as a regex.
+    pub fn parse(input: &str, regex: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut terms = Vec::new();
+
+        for token in tokenize(input) {
+            let (negated, token) = match token.strip_prefix('!') {
+                Some(rest) if !rest.is_empty() => (true, rest.to_string()),
+                _ => (false, token),
+            };
+
+            let (field, value) = match token.split_once(':') {
+                Some((name, value)) if !value.is_empty() => match Field::parse(name) {
+                    Some(field) => (Some(field), value.to_string()),
+                    None => (None, token.clone()),
+                },
+                _ => (None, token.clone()),
+            };
+
+            let slash_regex = value.len() > 1 && value.starts_with('/') && value.ends_with('/');
+            let matcher = if slash_regex || regex {
+                let pattern = if slash_regex { &value[1..value.len() - 1] } else { value.as_str() };
+                let regex = Regex::new(&format!("(?i){}", pattern))
+                    .map_err(|e| format!("Invalid regex '{}': {}", pattern, e))?;
+                Matcher::Regex(regex)
+            } else {
+                Matcher::Text(value.to_lowercase())
+            };
+
+            terms.push(Term { field, matcher, negated });
+        }
+
+        Ok(Self { terms })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Returns a relevance score if `item` matches every term, `None` otherwise.
+    pub fn score<T: Searchable>(&self, item: &T) -> Option<u32> {
+        let mut total = 0;
+
+        for term in &self.terms {
+            let score = match term.field {
+                Some(field) => item
+                    .field_values(field)
+                    .iter()
+                    .map(|value| term.matcher.score(value))
+                    .max()
+                    .unwrap_or(0),
+                None => T::default_fields()
+                    .iter()
+                    .flat_map(|&(field, weight)| {
+                        item.field_values(field)
+                            .into_iter()
+                            .map(move |value| (value, weight))
+                    })
+                    .map(|(value, weight)| term.matcher.score(value) * weight)
+                    .max()
+                    .unwrap_or(0),
+            };
+
+            match (score > 0, term.negated) {
+                (true, false) => total += score,
+                (false, true) => {}
+                _ => return None,
+            }
+        }
+
+        // Negated-only queries still match with a neutral score
+        Some(total.max(1))
+    }
+
+    /// Keeps the items matching the query, best matches first. The sort is
+    /// stable, so equally relevant items keep their incoming order.
+    pub fn rank<T: Searchable>(&self, items: Vec<T>) -> Vec<T> {
+        let mut scored: Vec<(u32, T)> = items
+            .into_iter()
+            .filter_map(|item| self.score(&item).map(|score| (score, item)))
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, item)| item).collect()
+    }
+}
+
+/// Splits on whitespace, keeping quoted sections and `/regex/` terms together.
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+
+    for c in input.chars() {
+        match quote {
+            // Regex delimiters stay in the token so the term is parsed as a regex
+            Some('/') if c == '/' && !current.ends_with('\\') => {
+                current.push(c);
+                quote = None;
+            }
+            Some(q) if c == q && q != '/' => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '/' && (current.is_empty() || current.ends_with(':') || current == "!") => {
+                current.push(c);
+                quote = Some(c);
+            }
+            None if c.is_whitespace() => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            None => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
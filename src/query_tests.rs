@@ -0,0 +1,64 @@
+use crate::{BookmarkItem, Command, Query};
+
+fn commands() -> Vec<Command> {
+    vec![
+        Command::new("list docker images".to_string(), "docker images".to_string()),
+        Command::new("remove container".to_string(), "docker rm web".to_string()),
+        Command::new("push code".to_string(), "git push origin main".to_string()),
+    ]
+}
+
+#[test]
+fn test_terms_must_all_match() {
+    let results = Query::parse("docker rm", false).unwrap().rank(commands());
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].command, "docker rm web");
+}
+
+#[test]
+fn test_field_scoped_terms() {
+    let query = Query::parse("desc:images", false).unwrap();
+    assert_eq!(query.rank(commands()).len(), 1);
+
+    let query = Query::parse("cmd:images", false).unwrap();
+    assert_eq!(query.rank(commands())[0].command, "docker images");
+
+    let query = Query::parse("desc:\"remove container\"", false).unwrap();
+    assert_eq!(query.rank(commands())[0].command, "docker rm web");
+}
+
+#[test]
+fn test_regex_and_negation() {
+    let query = Query::parse("/^(git|docker) (push|rm)/", false).unwrap();
+    assert_eq!(query.rank(commands()).len(), 2);
+
+    let query = Query::parse("docker !cmd:rm", false).unwrap();
+    let results = query.rank(commands());
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].command, "docker images");
+
+    let query = Query::parse("push|images", true).unwrap();
+    assert_eq!(query.rank(commands()).len(), 2);
+    assert!(Query::parse("/(/", false).is_err());
+}
+
+#[test]
+fn test_ranking_prefers_better_matches() {
+    let item = |name: &str, command: &str| BookmarkItem {
+        name: name.to_string(),
+        command: command.to_string(),
+        description: String::new(),
+        tags: vec!["docker".to_string()],
+        created_at: 0,
+        ..Default::default()
+    };
+    let items = vec![
+        item("cleanup", "docker system prune -af"),
+        item("docker-logs", "docker logs -f"),
+        item("docker", "docker ps"),
+    ];
+
+    let results = Query::parse("docker", false).unwrap().rank(items);
+    let names: Vec<&str> = results.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(names, vec!["docker", "docker-logs", "cleanup"]);
+}
@@ -0,0 +1,116 @@
+use std::fs;
+use regex::Regex;
+use crate::config::env_bool;
+use crate::storage::get_config_dir;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Built-in secret patterns. When a pattern has a `secret` group only that
+/// group is replaced, so the surrounding flag or header name stays readable.
+const BUILTIN_PATTERNS: &[&str] = &[
+    // Authorization headers
+    r"(?i)\b(?:authorization|proxy-authorization):\s*(?:bearer|basic|token)\s+(?P<secret>[^\s'\x22]+)",
+    // Credential flags: --password=..., --token ...
+    r#"(?i)(?:^|\s)--(?:password|passwd|pass|token|api-key|apikey|secret|client-secret)(?:=|\s+)(?P<secret>'[^']*'|"[^"]*"|[^\s'"]+)"#,
+    // Inline -p passwords for tools that take them
+    r"\b(?:mysql|mysqldump|mysqladmin|mariadb|sshpass)\b[^|;&]*?\s-p\s*(?P<secret>[^\s-]\S*)",
+    // Environment assignments such as AWS_SECRET_ACCESS_KEY=...
+    r#"(?i)\b[A-Z0-9_]*(?:SECRET|TOKEN|PASSWORD|PASSWD|API_KEY|APIKEY|ACCESS_KEY|PRIVATE_KEY)[A-Z0-9_]*=(?P<secret>'[^']*'|"[^"]*"|\S+)"#,
+    // Credentials embedded in URLs
+    r"://[^/\s:@]+:(?P<secret>[^/\s@]+)@",
+    // AWS access key ids
+    r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b",
+    // GitHub tokens
+    r"\bgh[pousr]_[A-Za-z0-9]{36,}\b",
+    r"\bgithub_pat_[A-Za-z0-9_]{22,}\b",
+    // Slack tokens
+    r"\bxox[abposr]-[A-Za-z0-9-]{10,}",
+    // OpenAI-style API keys
+    r"\bsk-[A-Za-z0-9_-]{20,}",
+    // JSON web tokens
+    r"\beyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+",
+];
+
+/// Masks secrets in commands before they are stored or sent to the model.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    patterns: Vec<Regex>,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        let patterns = BUILTIN_PATTERNS
+            .iter()
+            .map(|p| Regex::new(p).expect("built-in redaction pattern must compile"))
+            .collect();
+        Self { patterns }
+    }
+}
+
+impl Redactor {
+    /// A redactor that leaves text untouched.
+    pub fn disabled() -> Self {
+        Self { patterns: Vec::new() }
+    }
+
+    /// Builds the redactor from the environment.
+    ///
+    /// `SHAI_REDACT=false` disables redaction. User patterns are read from
+    /// `SHAI_REDACT_PATTERNS_FILE` (default `<config dir>/redact-patterns`),
+    /// one regex per line, with `#` comments.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        if !env_bool("SHAI_REDACT", true) {
+            return Ok(Self::disabled());
+        }
+
+        let path = match std::env::var("SHAI_REDACT_PATTERNS_FILE") {
+            Ok(path) if !path.is_empty() => Some(path.into()),
+            _ => get_config_dir().ok().map(|dir| dir.join("redact-patterns")),
+        };
+
+        let mut redactor = Self::default();
+        if let Some(path) = path.filter(|p: &std::path::PathBuf| p.exists()) {
+            let content = fs::read_to_string(&path)?;
+            let patterns = content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'));
+            redactor = redactor.with_patterns(patterns)?;
+        }
+        Ok(redactor)
+    }
+
+    /// Adds user patterns on top of the current ones.
+    pub fn with_patterns<'a>(
+        mut self,
+        patterns: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        for pattern in patterns {
+            let regex = Regex::new(pattern)
+                .map_err(|e| format!("Invalid redaction pattern '{}': {}", pattern, e))?;
+            self.patterns.push(regex);
+        }
+        Ok(self)
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut result = text.to_string();
+        for pattern in &self.patterns {
+            result = pattern
+                .replace_all(&result, |caps: &regex::Captures| {
+                    let whole = caps.get(0).unwrap();
+                    match caps.name("secret") {
+                        Some(secret) => {
+                            let start = secret.start() - whole.start();
+                            let end = secret.end() - whole.start();
+                            let text = whole.as_str();
+                            format!("{}{}{}", &text[..start], REDACTED, &text[end..])
+                        }
+                        None => REDACTED.to_string(),
+                    }
+                })
+                .into_owned();
+        }
+        result
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashSet;
+use crate::command::Command;
+use crate::config::{env_bool, env_number, env_value};
+
+/// Rules deciding which history entries are pruned.
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    /// Maximum number of entries to keep
+    pub max_entries: Option<usize>,
+    /// Drop entries older than this many days
+    pub max_age_days: Option<u64>,
+    /// Maximum total size of the stored entries in bytes
+    pub max_bytes: Option<u64>,
+    /// Never prune entries whose command is bookmarked
+    pub keep_bookmarked: bool,
+    /// Never prune starred entries
+    pub keep_starred: bool,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_entries: Some(1000),
+            max_age_days: None,
+            max_bytes: None,
+            keep_bookmarked: true,
+            keep_starred: true,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Reads `SHAI_HISTORY_MAX_ENTRIES` (`0` for unlimited), `SHAI_HISTORY_MAX_AGE_DAYS`,
+    /// `SHAI_HISTORY_MAX_SIZE` (bytes, or with a `K`/`M`/`G` suffix),
+    /// `SHAI_HISTORY_KEEP_BOOKMARKED` and `SHAI_HISTORY_KEEP_STARRED`.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut policy = Self::default();
+
+        if let Some(max) = env_number::<usize>("SHAI_HISTORY_MAX_ENTRIES")? {
+            policy.max_entries = (max > 0).then_some(max);
+        }
+        if let Some(days) = env_number("SHAI_HISTORY_MAX_AGE_DAYS")? {
+            policy.max_age_days = Some(days);
+        }
+        if let Some(value) = env_value("SHAI_HISTORY_MAX_SIZE") {
+            policy.max_bytes = Some(parse_size(&value)?);
+        }
+        policy.keep_bookmarked = env_bool("SHAI_HISTORY_KEEP_BOOKMARKED", policy.keep_bookmarked);
+        policy.keep_starred = env_bool("SHAI_HISTORY_KEEP_STARRED", policy.keep_starred);
+
+        Ok(policy)
+    }
+
+    /// Returns the indices of `commands` to prune, given the set of bookmarked
+    /// command strings. Entries imported from shell history go before
+    /// entries recorded by shai, so an import never pushes those out, and
+    /// oldest unprotected entries go first within each group.
+    pub fn select_pruned(&self, commands: &[Command], bookmarked: &HashSet<String>, now: i64) -> Vec<usize> {
+        let is_protected = |cmd: &Command| {
+            (self.keep_starred && cmd.starred) || (self.keep_bookmarked && bookmarked.contains(&cmd.command))
+        };
+
+        let mut order: Vec<usize> = (0..commands.len()).collect();
+        order.sort_by_key(|&i| (commands[i].source.is_none(), commands[i].last_used()));
+        let candidates: Vec<usize> = order.into_iter().filter(|&i| !is_protected(&commands[i])).collect();
+
+        let mut pruned = vec![false; commands.len()];
+        let mut remaining = commands.len();
+        // Serializing every entry is only worth it when a size limit is set
+        let track_size = self.max_bytes.is_some();
+        let mut total_bytes: u64 = if track_size { commands.iter().map(entry_size).sum() } else { 0 };
+        let mut prune = |i: usize, remaining: &mut usize, total_bytes: &mut u64| {
+            pruned[i] = true;
+            *remaining -= 1;
+            if track_size {
+                *total_bytes -= entry_size(&commands[i]);
+            }
+        };
+
+        let cutoff = self.max_age_days.map(|days| now - (days as i64) * 24 * 60 * 60);
+        let is_old = |i: usize| cutoff.is_some_and(|cutoff| commands[i].last_used() < cutoff);
+        let (old, candidates): (Vec<usize>, Vec<usize>) = candidates.into_iter().partition(|&i| is_old(i));
+        for i in old {
+            prune(i, &mut remaining, &mut total_bytes);
+        }
+
+        let mut candidates = candidates.into_iter();
+        while self.max_entries.is_some_and(|max| remaining > max)
+            || self.max_bytes.is_some_and(|max| total_bytes > max)
+        {
+            match candidates.next() {
+                Some(i) => prune(i, &mut remaining, &mut total_bytes),
+                None => break,
+            }
+        }
+
+        (0..commands.len()).filter(|&i| pruned[i]).collect()
+    }
+}
+
+fn entry_size(command: &Command) -> u64 {
+    serde_json::to_string(command).map(|s| s.len() as u64).unwrap_or(0)
+}
+
+fn parse_size(value: &str) -> Result<u64, Box<dyn std::error::Error>> {
+    let upper = value.to_uppercase();
+    let trimmed = upper.trim_end_matches('B');
+    let (number, multiplier) = match trimmed.chars().last() {
+        Some('K') => (&trimmed[..trimmed.len() - 1], 1024),
+        Some('M') => (&trimmed[..trimmed.len() - 1], 1024 * 1024),
+        Some('G') => (&trimmed[..trimmed.len() - 1], 1024 * 1024 * 1024),
+        _ => (trimmed, 1),
+    };
+    let number: u64 = number.trim().parse()
+        .map_err(|_| format!("Invalid size '{}': expected e.g. 500K or 2M", value))?;
+    Ok(number * multiplier)
+}
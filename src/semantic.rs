@@ -0,0 +1,234 @@
+use std::path::PathBuf;
+use openai_api_rs::v1::api::OpenAIClient;
+use openai_api_rs::v1::embedding::EmbeddingRequest;
+use serde::{Deserialize, Serialize};
+use crate::bookmark::BookmarkItem;
+use crate::redact::Redactor;
+use crate::storage::{Storage, Encryption, get_data_dir};
+
+const HASH_DIMENSIONS: usize = 512;
+
+const STOPWORDS: &[&str] = &[
+    "a", "all", "an", "and", "by", "for", "from", "in", "is", "it", "my", "of", "on", "or",
+    "that", "the", "this", "to", "up", "with",
+];
+
+/// How texts are turned into vectors.
+pub enum Embedder<'a> {
+    /// Local feature hashing of words and character trigrams; works offline
+    /// but only matches shared vocabulary
+    Hashing,
+    /// The provider's embeddings endpoint
+    Provider {
+        client: &'a OpenAIClient,
+        model: &'a str,
+        redactor: &'a Redactor,
+    },
+}
+
+impl Embedder<'_> {
+    /// Identifies the vector space, so the index is rebuilt when it changes.
+    pub fn id(&self) -> String {
+        match self {
+            Embedder::Hashing => format!("hashing-{}", HASH_DIMENSIONS),
+            Embedder::Provider { model, .. } => format!("provider:{}", model),
+        }
+    }
+
+    /// Lowest similarity still considered a match.
+    pub fn threshold(&self) -> f32 {
+        match self {
+            Embedder::Hashing => 0.2,
+            Embedder::Provider { .. } => 0.35,
+        }
+    }
+
+    pub async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
+        match self {
+            Embedder::Hashing => Ok(texts.iter().map(|t| hash_embedding(t)).collect()),
+            Embedder::Provider { client, model, redactor } => {
+                if texts.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let input = texts.iter().map(|t| redactor.redact(t)).collect();
+                let response = client.embedding(EmbeddingRequest::new(model.to_string(), input)).await?;
+                let mut data = response.data;
+                data.sort_by_key(|d| d.index);
+                if data.len() != texts.len() {
+                    return Err(format!("Expected {} embeddings, got {}", texts.len(), data.len()).into());
+                }
+                Ok(data.into_iter().map(|d| normalize(d.embedding)).collect())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    name: String,
+    /// Fingerprint of the text the vector was computed from
+    fingerprint: String,
+    vector: Vec<f32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexData {
+    embedder: String,
+    entries: Vec<IndexEntry>,
+}
+
+/// Bookmark embeddings, stored next to the bookmarks file and refreshed for
+/// bookmarks whose text changed.
+pub struct EmbeddingIndex {
+    path: PathBuf,
+    encryption: Option<Encryption>,
+}
+
+impl Storage for EmbeddingIndex {
+    fn get_storage_path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    fn get_encryption(&self) -> Option<&Encryption> {
+        self.encryption.as_ref()
+    }
+}
+
+impl EmbeddingIndex {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut index = Self::with_path(get_data_dir()?.join("bookmark-index.json"));
+        index.encryption = Encryption::from_env()?;
+        Ok(index)
+    }
+
+    /// Opens the index file at `path`.
+    pub fn with_path(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), encryption: None }
+    }
+
+    pub fn with_encryption(mut self, encryption: Encryption) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    /// Bookmarks similar in meaning to `query`, most similar first, with their
+    /// cosine similarity. The index is brought up to date with `items` first.
+    pub async fn search(
+        &self,
+        items: Vec<BookmarkItem>,
+        query: &str,
+        embedder: &Embedder<'_>,
+        limit: usize,
+    ) -> Result<Vec<(BookmarkItem, f32)>, Box<dyn std::error::Error>> {
+        if items.is_empty() || query.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let data = self.refresh(&items, embedder).await?;
+        let query_vector = embedder.embed(vec![query.to_string()]).await?.pop().unwrap_or_default();
+
+        let mut scored: Vec<(BookmarkItem, f32)> = items
+            .into_iter()
+            .filter_map(|item| {
+                let entry = data.entries.iter().find(|e| e.name == item.name)?;
+                let score = dot(&entry.vector, &query_vector);
+                (score >= embedder.threshold()).then_some((item, score))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(limit);
+        Ok(scored)
+    }
+
+    /// Embeds new and changed bookmarks and drops removed ones.
+    async fn refresh(&self, items: &[BookmarkItem], embedder: &Embedder<'_>) -> Result<IndexData, Box<dyn std::error::Error>> {
+        let mut data = self.load_data()?;
+        if data.embedder != embedder.id() {
+            data = IndexData { embedder: embedder.id(), entries: Vec::new() };
+        }
+
+        let before = data.entries.len();
+        data.entries.retain(|e| {
+            items.iter().any(|item| item.name == e.name && fingerprint(&index_text(item)) == e.fingerprint)
+        });
+        let stale: Vec<&BookmarkItem> = items
+            .iter()
+            .filter(|item| !data.entries.iter().any(|e| e.name == item.name))
+            .collect();
+
+        if stale.is_empty() && data.entries.len() == before && self.get_storage_path().exists() {
+            return Ok(data);
+        }
+
+        let texts: Vec<String> = stale.iter().map(|item| index_text(item)).collect();
+        let vectors = embedder.embed(texts.clone()).await?;
+        for ((item, text), vector) in stale.into_iter().zip(texts).zip(vectors) {
+            data.entries.push(IndexEntry { name: item.name.clone(), fingerprint: fingerprint(&text), vector });
+        }
+
+        self.save(&data)?;
+        Ok(data)
+    }
+
+    fn load_data(&self) -> Result<IndexData, Box<dyn std::error::Error>> {
+        if !self.get_storage_path().exists() {
+            return Ok(IndexData::default());
+        }
+        self.load::<IndexData>()
+    }
+}
+
+/// The text a bookmark is embedded from.
+fn index_text(item: &BookmarkItem) -> String {
+    format!(
+        "{}\n{}\n{}\n{}\n{}",
+        item.name.replace(['-', '_', '/'], " "),
+        item.description,
+        item.tags.join(" "),
+        item.command,
+        item.notes
+    )
+}
+
+/// Feature-hashed bag of words and character trigrams, L2-normalized.
+pub fn hash_embedding(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0; HASH_DIMENSIONS];
+    let lowered = text.to_lowercase();
+    let words = lowered
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty() && !STOPWORDS.contains(w));
+
+    for word in words {
+        vector[(fnv1a(word.as_bytes()) % HASH_DIMENSIONS as u64) as usize] += 1.0;
+
+        // Trigrams let related word forms ("prune", "pruning") overlap
+        let padded: Vec<char> = format!(" {} ", word).chars().collect();
+        for trigram in padded.windows(3) {
+            let trigram: String = trigram.iter().collect();
+            vector[(fnv1a(trigram.as_bytes()) % HASH_DIMENSIONS as u64) as usize] += 0.3;
+        }
+    }
+
+    normalize(vector)
+}
+
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+/// Dot product; cosine similarity for normalized vectors.
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// FNV-1a, used because it is stable across Rust versions, unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn fingerprint(text: &str) -> String {
+    format!("{:016x}", fnv1a(text.as_bytes()))
+}
@@ -0,0 +1,68 @@
+use crate::semantic::hash_embedding;
+use crate::{BookmarkItem, Embedder, EmbeddingIndex};
+use tempfile::TempDir;
+
+fn item(name: &str, command: &str, description: &str) -> BookmarkItem {
+    BookmarkItem {
+        name: name.to_string(),
+        command: command.to_string(),
+        description: description.to_string(),
+        ..Default::default()
+    }
+}
+
+fn bookmarks() -> Vec<BookmarkItem> {
+    vec![
+        item("docker-clean", "docker system prune -af", "Remove unused Docker data to free disk space"),
+        item("list-files", "ls -la", "List all files in the current directory"),
+        item("git-undo", "git reset --soft HEAD~1", "Undo the last commit but keep changes"),
+    ]
+}
+
+#[test]
+fn test_hash_embedding_is_normalized() {
+    let vector = hash_embedding("free up disk space");
+    let norm: f32 = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    assert!((norm - 1.0).abs() < 1e-5);
+    assert!(hash_embedding("").iter().all(|v| *v == 0.0));
+}
+
+#[tokio::test]
+async fn test_semantic_search_with_hashing() {
+    let temp_dir = TempDir::new().unwrap();
+    let index = EmbeddingIndex::with_path(temp_dir.path().join("index.json"));
+
+    let results = index.search(bookmarks(), "free up disk space", &Embedder::Hashing, 5).await.unwrap();
+    assert_eq!(results[0].0.name, "docker-clean");
+    assert!(results.iter().all(|(item, _)| item.name != "list-files"));
+
+    let results = index.search(bookmarks(), "undo my last commit", &Embedder::Hashing, 1).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0.name, "git-undo");
+    assert!(temp_dir.path().join("index.json").exists());
+}
+
+#[tokio::test]
+async fn test_semantic_search_without_match() {
+    let temp_dir = TempDir::new().unwrap();
+    let index = EmbeddingIndex::with_path(temp_dir.path().join("index.json"));
+
+    let results = index.search(bookmarks(), "restart nginx", &Embedder::Hashing, 5).await.unwrap();
+    assert!(results.is_empty());
+}
+
+#[tokio::test]
+async fn test_semantic_index_follows_changes() {
+    let temp_dir = TempDir::new().unwrap();
+    let index = EmbeddingIndex::with_path(temp_dir.path().join("index.json"));
+    index.search(bookmarks(), "disk", &Embedder::Hashing, 5).await.unwrap();
+
+    let mut changed = bookmarks();
+    changed[1].description = "Show disk usage per directory".to_string();
+    changed[1].command = "du -sh *".to_string();
+    changed.remove(0);
+
+    let results = index.search(changed, "disk usage", &Embedder::Hashing, 5).await.unwrap();
+    assert_eq!(results[0].0.name, "list-files");
+    assert!(results.iter().all(|(item, _)| item.name != "docker-clean"));
+}
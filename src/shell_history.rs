@@ -0,0 +1,177 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::command::Command;
+use crate::storage::{home_dir, get_data_home};
+
+/// Shells whose native history files can be imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        }
+    }
+
+    /// The user's shell according to `$SHELL`, if it is one of these.
+    pub fn detect() -> Option<Self> {
+        let shell = std::env::var("SHELL").ok()?;
+        match shell.rsplit('/').next()? {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+
+    /// Default history file location for the shell.
+    pub fn default_history_path(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let home = home_dir().ok_or("Cannot determine home directory")?;
+        let path = match self {
+            Shell::Bash => home.join(".bash_history"),
+            Shell::Zsh => {
+                let zsh_history = home.join(".zsh_history");
+                if zsh_history.exists() { zsh_history } else { home.join(".zhistory") }
+            }
+            Shell::Fish => get_data_home()?.join("fish").join("fish_history"),
+        };
+        Ok(path)
+    }
+
+    /// Parses history file contents into entries tagged with this shell as
+    /// source, in file order. Entries the file has no timestamp for get `0`;
+    /// `History::import` dates them.
+    pub fn parse(&self, content: &[u8]) -> Vec<Command> {
+        let entries = match self {
+            Shell::Bash => parse_bash(&String::from_utf8_lossy(content)),
+            Shell::Zsh => parse_zsh(&String::from_utf8_lossy(&unmetafy(content))),
+            Shell::Fish => parse_fish(&String::from_utf8_lossy(content)),
+        };
+
+        entries
+            .into_iter()
+            .map(|(timestamp, command)| {
+                let mut entry = Command::new(String::new(), command).with_executed(true);
+                entry.timestamp = timestamp.unwrap_or(0);
+                entry.source = Some(self.name().to_string());
+                entry
+            })
+            .collect()
+    }
+
+    pub fn read(&self, path: &Path) -> Result<Vec<Command>, Box<dyn std::error::Error>> {
+        let content = fs::read(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        Ok(self.parse(&content))
+    }
+}
+
+/// Bash history: one command per line, optionally preceded by `#<epoch>`
+/// lines when `HISTTIMEFORMAT` is set.
+fn parse_bash(content: &str) -> Vec<(Option<i64>, String)> {
+    let mut entries = Vec::new();
+    let mut timestamp = None;
+
+    for line in content.lines() {
+        if let Some(ts) = line.strip_prefix('#').and_then(|rest| rest.trim().parse().ok()) {
+            timestamp = Some(ts);
+            continue;
+        }
+        if !line.trim().is_empty() {
+            entries.push((timestamp.take(), line.to_string()));
+        }
+    }
+
+    entries
+}
+
+/// Zsh history, plain or in `EXTENDED_HISTORY` format (`: <epoch>:<duration>;<command>`).
+/// Lines ending in a backslash continue the command on the next line.
+fn parse_zsh(content: &str) -> Vec<(Option<i64>, String)> {
+    let mut entries: Vec<(Option<i64>, String)> = Vec::new();
+    let mut continuing = false;
+
+    for line in content.lines() {
+        if continuing {
+            if let Some((_, command)) = entries.last_mut() {
+                command.pop();
+                command.push('\n');
+                command.push_str(line);
+            }
+        } else if let Some((timestamp, command)) = parse_zsh_extended(line) {
+            entries.push((Some(timestamp), command.to_string()));
+        } else if !line.trim().is_empty() {
+            entries.push((None, line.to_string()));
+        } else {
+            continue;
+        }
+        continuing = line.ends_with('\\');
+    }
+
+    entries
+}
+
+fn parse_zsh_extended(line: &str) -> Option<(i64, &str)> {
+    let rest = line.strip_prefix(": ")?;
+    let (meta, command) = rest.split_once(';')?;
+    let (timestamp, _duration) = meta.split_once(':')?;
+    Some((timestamp.trim().parse().ok()?, command))
+}
+
+/// Zsh escapes bytes 0x83-0x9f in its history file as 0x83 followed by the
+/// byte XOR 32.
+fn unmetafy(content: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(content.len());
+    let mut bytes = content.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte == 0x83 {
+            if let Some(&next) = bytes.next() {
+                result.push(next ^ 32);
+            }
+        } else {
+            result.push(byte);
+        }
+    }
+    result
+}
+
+/// Fish history, a YAML-like list of `- cmd: ...` items with `when:` timestamps.
+fn parse_fish(content: &str) -> Vec<(Option<i64>, String)> {
+    let mut entries: Vec<(Option<i64>, String)> = Vec::new();
+
+    for line in content.lines() {
+        if let Some(command) = line.strip_prefix("- cmd: ") {
+            entries.push((None, unescape_fish(command)));
+        } else if let Some(when) = line.trim_start().strip_prefix("when: ") {
+            if let Some((timestamp, _)) = entries.last_mut() {
+                *timestamp = when.trim().parse().ok();
+            }
+        }
+    }
+
+    entries
+}
+
+fn unescape_fish(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some(other) => result.push(other),
+                None => result.push('\\'),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
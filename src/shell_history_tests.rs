@@ -0,0 +1,52 @@
+use crate::Shell;
+
+#[test]
+fn test_parse_bash_with_timestamps() {
+    let content = b"ls -la\n#1700000000\ngit status\n\n";
+    let commands = Shell::Bash.parse(content);
+
+    assert_eq!(commands.len(), 2);
+    assert_eq!(commands[0].command, "ls -la");
+    assert_eq!(commands[1].command, "git status");
+    assert_eq!(commands[1].timestamp, 1700000000);
+    assert_eq!(commands[1].source.as_deref(), Some("bash"));
+}
+
+#[test]
+fn test_parse_bash_without_timestamps() {
+    let commands = Shell::Bash.parse(b"ls -la\ngit status\n");
+
+    assert_eq!(commands[0].command, "ls -la");
+    assert!(commands.iter().all(|c| c.timestamp == 0));
+}
+
+#[test]
+fn test_parse_zsh_extended() {
+    let content = b": 1700000000:0;echo one\n: 1700000005:2;for i in 1 2; do\\\necho $i\\\ndone\necho plain\n";
+    let commands = Shell::Zsh.parse(content);
+
+    assert_eq!(commands.len(), 3);
+    assert_eq!(commands[0].timestamp, 1700000000);
+    assert_eq!(commands[1].command, "for i in 1 2; do\necho $i\ndone");
+    assert_eq!(commands[2].command, "echo plain");
+}
+
+#[test]
+fn test_parse_zsh_metafied() {
+    // "ă" is 0xc4 0x83, and zsh stores 0x83 as 0x83 0xa3
+    let content = b": 1700000000:0;echo \xc4\x83\xa3\n";
+    let commands = Shell::Zsh.parse(content);
+
+    assert_eq!(commands[0].command, "echo ă");
+}
+
+#[test]
+fn test_parse_fish() {
+    let content = b"- cmd: cargo build\n  when: 1700000000\n- cmd: echo a\\\\nb\n  when: 1700000010\n  paths:\n    - src\n";
+    let commands = Shell::Fish.parse(content);
+
+    assert_eq!(commands.len(), 2);
+    assert_eq!(commands[0].command, "cargo build");
+    assert_eq!(commands[1].command, "echo a\\nb");
+    assert_eq!(commands[1].timestamp, 1700000010);
+}
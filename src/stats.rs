@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use chrono::{Local, TimeZone, Timelike};
+use serde::Serialize;
+use crate::command::Command;
+
+#[derive(Debug, Serialize)]
+pub struct CountEntry {
+    pub name: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RateEntry {
+    pub name: String,
+    pub total: usize,
+    pub matched: usize,
+    pub rate: f64,
+}
+
+/// Aggregated usage report over history entries.
+#[derive(Debug, Serialize)]
+pub struct HistoryStats {
+    pub total: usize,
+    pub executed: usize,
+    pub top_commands: Vec<CountEntry>,
+    pub top_programs: Vec<CountEntry>,
+    /// Share of entries per model whose suggestion was edited before running
+    pub edit_rate_by_model: Vec<RateEntry>,
+    /// Share of runs per program that exited non-zero, for entries with an exit code
+    pub failure_rate_by_program: Vec<RateEntry>,
+    /// Entries per local hour of day, busiest first
+    pub busiest_hours: Vec<CountEntry>,
+    /// Share of AI suggestions that were run without edits
+    pub suggestion_acceptance_rate: Option<f64>,
+}
+
+impl HistoryStats {
+    pub fn from_commands(commands: &[Command], top: usize) -> Self {
+        let mut command_counts: HashMap<String, usize> = HashMap::new();
+        let mut program_counts: HashMap<String, usize> = HashMap::new();
+        let mut model_edits: HashMap<String, (usize, usize)> = HashMap::new();
+        let mut program_failures: HashMap<String, (usize, usize)> = HashMap::new();
+        let mut hour_counts: HashMap<String, usize> = HashMap::new();
+        let mut suggested = 0;
+        let mut accepted = 0;
+
+        for cmd in commands {
+            let count = cmd.count as usize;
+            *command_counts.entry(cmd.command.clone()).or_default() += count;
+
+            let program = program_name(&cmd.command);
+            if let Some(program) = &program {
+                *program_counts.entry(program.clone()).or_default() += count;
+            }
+
+            if let Some(model) = &cmd.model {
+                let entry = model_edits.entry(model.clone()).or_default();
+                entry.0 += 1;
+                if cmd.edited {
+                    entry.1 += 1;
+                }
+            }
+
+            if let (Some(program), Some(code)) = (&program, cmd.exit_code) {
+                let entry = program_failures.entry(program.clone()).or_default();
+                entry.0 += 1;
+                if code != 0 {
+                    entry.1 += 1;
+                }
+            }
+
+            if let Some(time) = Local.timestamp_opt(cmd.timestamp, 0).single() {
+                *hour_counts.entry(format!("{:02}:00", time.hour())).or_default() += 1;
+            }
+
+            if !cmd.suggestions.is_empty() {
+                suggested += 1;
+                if !cmd.edited {
+                    accepted += 1;
+                }
+            }
+        }
+
+        Self {
+            total: commands.len(),
+            executed: commands.iter().filter(|c| c.executed).count(),
+            top_commands: top_counts(command_counts, top),
+            top_programs: top_counts(program_counts, top),
+            edit_rate_by_model: rates(model_edits),
+            failure_rate_by_program: rates(program_failures),
+            busiest_hours: top_counts(hour_counts, top),
+            suggestion_acceptance_rate: (suggested > 0).then(|| accepted as f64 / suggested as f64),
+        }
+    }
+}
+
+/// Returns the executable a command line runs, skipping env assignments
+/// and wrappers such as `sudo`.
+pub fn program_name(command: &str) -> Option<String> {
+    const WRAPPERS: &[&str] = &["sudo", "env", "time", "nohup", "exec", "command"];
+
+    command
+        .split_whitespace()
+        .find(|word| !word.contains('=') && !WRAPPERS.contains(word))
+        .map(|word| word.rsplit('/').next().unwrap_or(word).to_string())
+}
+
+fn top_counts(counts: HashMap<String, usize>, top: usize) -> Vec<CountEntry> {
+    let mut entries: Vec<CountEntry> = counts
+        .into_iter()
+        .map(|(name, count)| CountEntry { name, count })
+        .collect();
+    entries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    entries.truncate(top);
+    entries
+}
+
+fn rates(counts: HashMap<String, (usize, usize)>) -> Vec<RateEntry> {
+    let mut entries: Vec<RateEntry> = counts
+        .into_iter()
+        .map(|(name, (total, matched))| RateEntry {
+            name,
+            total,
+            matched,
+            rate: matched as f64 / total as f64,
+        })
+        .collect();
+    entries.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.name.cmp(&b.name)));
+    entries
+}
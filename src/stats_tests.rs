@@ -0,0 +1,36 @@
+use crate::stats::program_name;
+use crate::{Command, HistoryStats};
+
+#[test]
+fn test_program_name() {
+    assert_eq!(program_name("sudo apt update").as_deref(), Some("apt"));
+    assert_eq!(program_name("RUST_LOG=debug /usr/bin/cargo run").as_deref(), Some("cargo"));
+    assert_eq!(program_name("   "), None);
+}
+
+#[test]
+fn test_history_stats() {
+    let mut failed = Command::new("build".to_string(), "cargo build".to_string())
+        .with_model("model-a", "https://example.com")
+        .with_suggestions(vec!["cargo build".to_string()]);
+    failed.exit_code = Some(101);
+
+    let edited = Command::new("test".to_string(), "cargo test --all".to_string())
+        .with_model("model-a", "https://example.com")
+        .with_suggestions(vec!["cargo test".to_string()])
+        .with_edited(true);
+
+    let mut listed = Command::new("list".to_string(), "ls -la".to_string());
+    listed.exit_code = Some(0);
+
+    let stats = HistoryStats::from_commands(&[failed, edited, listed.clone(), listed], 5);
+
+    assert_eq!(stats.total, 4);
+    assert_eq!(stats.top_commands[0].name, "ls -la");
+    assert_eq!(stats.top_programs[0].name, "cargo");
+    assert_eq!(stats.edit_rate_by_model[0].rate, 0.5);
+    assert_eq!(stats.suggestion_acceptance_rate, Some(0.5));
+
+    let cargo = stats.failure_rate_by_program.iter().find(|r| r.name == "cargo").unwrap();
+    assert_eq!((cargo.total, cargo.matched), (1, 1));
+}
@@ -1,10 +1,19 @@
 use std::fs;
-use std::path::PathBuf;
-use serde::{Serialize, de::DeserializeOwned};
+use std::path::{Path, PathBuf};
+use argon2::Argon2;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce, Key};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, rand_core::RngCore};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
 
 pub trait Storage {
     fn get_storage_path(&self) -> PathBuf;
-    
+
+    /// Encryption applied to the file contents, if any.
+    fn get_encryption(&self) -> Option<&Encryption> {
+        None
+    }
+
     fn ensure_storage_dir(&self) -> Result<(), Box<dyn std::error::Error>> {
         let path = self.get_storage_path();
         if let Some(parent) = path.parent() {
@@ -12,28 +21,249 @@ pub trait Storage {
         }
         Ok(())
     }
-    
+
     fn load<T: DeserializeOwned>(&self) -> Result<T, Box<dyn std::error::Error>> {
         let path = self.get_storage_path();
         let content = fs::read_to_string(path)?;
-        let data = serde_json::from_str(&content)?;
+
+        let data = match serde_json::from_str::<EncryptedFile>(&content) {
+            Ok(file) => {
+                let encryption = self.get_encryption().ok_or(
+                    "Data file is encrypted: set SHAI_ENCRYPTION_PASSPHRASE or SHAI_ENCRYPTION_KEY_FILE",
+                )?;
+                serde_json::from_slice(&encryption.decrypt(&file)?)?
+            }
+            // Plaintext files are still readable so existing data can be
+            // migrated; the next save encrypts them. Say so, since this is
+            // also what a file swapped in by someone else would look like.
+            Err(_) => {
+                if self.get_encryption().is_some() {
+                    eprintln!(
+                        "Warning: {} is not encrypted; it will be encrypted on the next write",
+                        self.get_storage_path().display()
+                    );
+                }
+                serde_json::from_str(&content)?
+            }
+        };
         Ok(data)
     }
-    
+
     fn save<T: Serialize>(&self, data: &T) -> Result<(), Box<dyn std::error::Error>> {
         self.ensure_storage_dir()?;
         let path = self.get_storage_path();
         let content = serde_json::to_string_pretty(data)?;
+        let content = match self.get_encryption() {
+            Some(encryption) => serde_json::to_string_pretty(&encryption.encrypt(content.as_bytes())?)?,
+            None => content,
+        };
         fs::write(path, content)?;
         Ok(())
     }
 }
 
-pub fn get_data_dir() -> PathBuf {
-    let home = std::env::var("HOME")
-        .or_else(|_| std::env::var("USERPROFILE"))
-        .unwrap_or_else(|_| ".".to_string());
-    
-    PathBuf::from(home).join(".shai")
+/// On-disk envelope for an encrypted data file.
+#[derive(Serialize, Deserialize)]
+struct EncryptedFile {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Authenticated encryption (XChaCha20-Poly1305) with an Argon2id-derived key.
+///
+/// A fresh salt and nonce are generated on every save.
+#[derive(Clone)]
+pub struct Encryption {
+    secret: Vec<u8>,
 }
 
+impl Encryption {
+    pub fn from_passphrase(passphrase: &str) -> Self {
+        Self { secret: passphrase.as_bytes().to_vec() }
+    }
+
+    /// Uses the raw contents of `path` as the secret.
+    pub fn from_key_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let secret = fs::read(path)
+            .map_err(|e| format!("Cannot read key file {}: {}", path.display(), e))?;
+        if secret.is_empty() {
+            return Err(format!("Key file {} is empty", path.display()).into());
+        }
+        Ok(Self { secret })
+    }
+
+    /// Reads `SHAI_ENCRYPTION_KEY_FILE` or `SHAI_ENCRYPTION_PASSPHRASE`.
+    /// Returns `None` when neither is set.
+    pub fn from_env() -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        if let Some(key_file) = non_empty_var("SHAI_ENCRYPTION_KEY_FILE") {
+            return Ok(Some(Self::from_key_file(Path::new(&key_file))?));
+        }
+        Ok(non_empty_var("SHAI_ENCRYPTION_PASSPHRASE").map(|p| Self::from_passphrase(&p)))
+    }
+
+    fn derive_cipher(&self, salt: &[u8]) -> Result<XChaCha20Poly1305, Box<dyn std::error::Error>> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(&self.secret, salt, &mut key)
+            .map_err(|e| format!("Key derivation failed: {}", e))?;
+        Ok(XChaCha20Poly1305::new(Key::from_slice(&key)))
+    }
+
+    fn encrypt(&self, plaintext: &[u8]) -> Result<EncryptedFile, Box<dyn std::error::Error>> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self.derive_cipher(&salt)?
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| "Encryption failed")?;
+
+        Ok(EncryptedFile {
+            version: 1,
+            salt: BASE64.encode(salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+    }
+
+    fn decrypt(&self, file: &EncryptedFile) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        if file.version != 1 {
+            return Err(format!("Unsupported encrypted file version {}", file.version).into());
+        }
+        let salt = BASE64.decode(&file.salt)?;
+        let nonce = BASE64.decode(&file.nonce)?;
+        if nonce.len() != 24 {
+            return Err("Encrypted file has an invalid nonce".into());
+        }
+        let ciphertext = BASE64.decode(&file.ciphertext)?;
+
+        let plaintext = self.derive_cipher(&salt)?
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| "Decryption failed: wrong passphrase or key file, or the data was tampered with")?;
+        Ok(plaintext)
+    }
+}
+
+/// Returns the user's home directory from `HOME` (or `USERPROFILE` on Windows).
+pub fn home_dir() -> Option<PathBuf> {
+    non_empty_var("HOME")
+        .or_else(|| non_empty_var("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Resolves the directory holding history, bookmarks and other data files.
+///
+/// Lookup order: `SHAI_DATA_DIR`, then `$XDG_DATA_HOME/shai`, then
+/// `~/.local/share/shai`. Data left in the legacy `~/.shai` directory is moved
+/// to the new location the first time it is resolved.
+pub fn get_data_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let data_dir = non_empty_var("SHAI_DATA_DIR");
+    let explicit = data_dir.is_some();
+    let dir = resolve_data_dir(data_dir, non_empty_var("XDG_DATA_HOME"), home_dir())?;
+
+    if !explicit {
+        if let Some(home) = home_dir() {
+            migrate_legacy_dir(&home.join(".shai"), &dir)?;
+        }
+    }
+    Ok(dir)
+}
+
+/// [`get_data_dir`] for the given `SHAI_DATA_DIR`, `XDG_DATA_HOME` and home
+/// directory, without migrating anything.
+pub(crate) fn resolve_data_dir(
+    data_dir: Option<String>,
+    xdg_data_home: Option<String>,
+    home: Option<PathBuf>,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    match data_dir {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => Ok(resolve_data_home(xdg_data_home, home)?.join("shai")),
+    }
+}
+
+/// Returns `$XDG_DATA_HOME`, defaulting to `~/.local/share`.
+pub fn get_data_home() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    resolve_data_home(non_empty_var("XDG_DATA_HOME"), home_dir())
+}
+
+fn resolve_data_home(xdg_data_home: Option<String>, home: Option<PathBuf>) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    match (xdg_data_home, home) {
+        (Some(xdg), _) => Ok(PathBuf::from(xdg)),
+        (None, Some(home)) => Ok(home.join(".local").join("share")),
+        (None, None) => Err(missing_home()),
+    }
+}
+
+/// Resolves the directory holding shai's configuration files.
+///
+/// Lookup order: `$XDG_CONFIG_HOME/shai`, then `~/.config/shai`.
+pub fn get_config_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    match non_empty_var("XDG_CONFIG_HOME") {
+        Some(xdg) => Ok(PathBuf::from(xdg).join("shai")),
+        None => Ok(require_home()?.join(".config").join("shai")),
+    }
+}
+
+fn require_home() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    home_dir().ok_or_else(missing_home)
+}
+
+fn missing_home() -> Box<dyn std::error::Error> {
+    "Cannot determine home directory: set HOME, XDG_DATA_HOME or SHAI_DATA_DIR".into()
+}
+
+fn non_empty_var(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.is_empty())
+}
+
+/// Moves the `legacy` directory (`~/.shai`) to `target` if the target
+/// doesn't exist yet.
+pub(crate) fn migrate_legacy_dir(legacy: &Path, target: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if !legacy.is_dir() || target.exists() || legacy == target {
+        return Ok(());
+    }
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Rename fails across filesystems, so fall back to copying. The legacy
+    // directory is only removed once everything in it was copied.
+    if fs::rename(legacy, target).is_err() {
+        if copy_dir(legacy, target)? {
+            fs::remove_dir_all(legacy)?;
+        } else {
+            eprintln!(
+                "Copied {} to {}, but left it in place: it contains entries other than files and directories",
+                legacy.display(),
+                target.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the files and directories below `from` into `to`. Returns `false`
+/// if anything else, such as a symlink, was skipped.
+pub(crate) fn copy_dir(from: &Path, to: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+    fs::create_dir_all(to)?;
+    let mut complete = true;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let destination = to.join(entry.file_name());
+        if file_type.is_dir() {
+            complete &= copy_dir(&entry.path(), &destination)?;
+        } else if file_type.is_file() {
+            fs::copy(entry.path(), destination)?;
+        } else {
+            complete = false;
+        }
+    }
+
+    Ok(complete)
+}
@@ -0,0 +1,55 @@
+use std::fs;
+use std::path::PathBuf;
+use crate::storage::{copy_dir, migrate_legacy_dir, resolve_data_dir};
+use tempfile::TempDir;
+
+#[test]
+fn test_data_dir_resolution_order() {
+    let home = Some(PathBuf::from("/home/me"));
+    let explicit = Some("/data/shai".to_string());
+    let xdg = Some("/xdg/data".to_string());
+
+    assert_eq!(resolve_data_dir(explicit.clone(), xdg.clone(), home.clone()).unwrap(), PathBuf::from("/data/shai"));
+    assert_eq!(resolve_data_dir(None, xdg, home.clone()).unwrap(), PathBuf::from("/xdg/data/shai"));
+    assert_eq!(resolve_data_dir(None, None, home).unwrap(), PathBuf::from("/home/me/.local/share/shai"));
+    assert_eq!(resolve_data_dir(explicit, None, None).unwrap(), PathBuf::from("/data/shai"));
+    assert!(resolve_data_dir(None, None, None).is_err());
+}
+
+#[test]
+fn test_migrate_legacy_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    let legacy = temp_dir.path().join(".shai");
+    let target = temp_dir.path().join("data").join("shai");
+    fs::create_dir_all(legacy.join("nested")).unwrap();
+    fs::write(legacy.join("history.json"), "{}").unwrap();
+    fs::write(legacy.join("nested").join("notes.txt"), "keep").unwrap();
+
+    migrate_legacy_dir(&legacy, &target).unwrap();
+    assert!(!legacy.exists());
+    assert_eq!(fs::read_to_string(target.join("nested").join("notes.txt")).unwrap(), "keep");
+
+    // An existing target is left alone
+    fs::create_dir_all(&legacy).unwrap();
+    fs::write(legacy.join("history.json"), "old").unwrap();
+    migrate_legacy_dir(&legacy, &target).unwrap();
+    assert!(legacy.exists());
+    assert_eq!(fs::read_to_string(target.join("history.json")).unwrap(), "{}");
+}
+
+#[test]
+fn test_copy_dir_is_recursive() {
+    let temp_dir = TempDir::new().unwrap();
+    let from = temp_dir.path().join("from");
+    fs::create_dir_all(from.join("a").join("b")).unwrap();
+    fs::write(from.join("a").join("b").join("c.json"), "[]").unwrap();
+
+    assert!(copy_dir(&from, &temp_dir.path().join("to")).unwrap());
+    assert_eq!(fs::read_to_string(temp_dir.path().join("to/a/b/c.json")).unwrap(), "[]");
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(from.join("a"), from.join("link")).unwrap();
+        assert!(!copy_dir(&from, &temp_dir.path().join("to2")).unwrap());
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+/// A `{{name}}` parameter in a bookmark command.
+///
+/// Written as `{{name}}`, `{{name=default}}`, `{{name:a|b|c}}` to restrict the
+/// value to a list of choices, or `{{name:a|b|c=b}}` to do both. Braces that
+/// don't start with a valid name, like Go templates' `{{.Names}}`, are left as
+/// they are, and `\{{` is a literal `{{` for cases like `{{end}}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    pub name: String,
+    pub default: Option<String>,
+    pub choices: Vec<String>,
+}
+
+impl Placeholder {
+    /// Parses the text between `{{` and `}}`, or returns `None` when it isn't
+    /// a placeholder.
+    fn parse(spec: &str) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let (spec, default) = match spec.split_once('=') {
+            Some((spec, default)) => (spec, Some(default.to_string())),
+            None => (spec, None),
+        };
+        let (name, choices) = match spec.split_once(':') {
+            Some((name, choices)) => (
+                name.trim(),
+                choices.split('|').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect(),
+            ),
+            None => (spec.trim(), Vec::new()),
+        };
+
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+            return Ok(None);
+        }
+
+        let placeholder = Self { name: name.to_string(), default, choices };
+        if let Some(default) = &placeholder.default {
+            placeholder.validate(default)?;
+        }
+        Ok(Some(placeholder))
+    }
+
+    /// Checks `value` against the placeholder's choices, if any.
+    pub fn validate(&self, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.choices.is_empty() && !self.choices.iter().any(|c| c == value) {
+            return Err(format!(
+                "Invalid value '{}' for '{}': expected one of {}",
+                value,
+                self.name,
+                self.choices.join(", ")
+            ).into());
+        }
+        Ok(())
+    }
+}
+
+enum Segment {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// Splits `command` into literal text and placeholders, unescaping `\{{`.
+fn segments(command: &str) -> Result<Vec<Segment>, Box<dyn std::error::Error>> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut rest = command;
+
+    while let Some(start) = rest.find("{{") {
+        if rest[..start].ends_with('\\') {
+            text.push_str(&rest[..start - 1]);
+            text.push_str("{{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        let Some(end) = rest[start..].find("}}") else { break };
+        match Placeholder::parse(&rest[start + 2..start + end])? {
+            Some(placeholder) => {
+                text.push_str(&rest[..start]);
+                if !text.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                segments.push(Segment::Placeholder(placeholder));
+                rest = &rest[start + end + 2..];
+            }
+            None => {
+                text.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+            }
+        }
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+
+    Ok(segments)
+}
+
+/// Escapes each `{{` in `text` that would otherwise be read as a placeholder
+/// or an escape, so `text` renders back unchanged.
+pub fn escape_literal(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let is_escape = rest[..start].ends_with('\\');
+        let is_placeholder = rest[start..]
+            .find("}}")
+            .is_some_and(|end| !matches!(Placeholder::parse(&rest[start + 2..start + end]), Ok(None)));
+        result.push_str(&rest[..start]);
+        if is_escape || is_placeholder {
+            result.push('\\');
+        }
+        result.push_str("{{");
+        rest = &rest[start + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Returns the placeholders in `command` in order of first use. A name used
+/// more than once is listed once and takes its default and choices from the
+/// first occurrence that declares them.
+pub fn placeholders(command: &str) -> Result<Vec<Placeholder>, Box<dyn std::error::Error>> {
+    let mut result: Vec<Placeholder> = Vec::new();
+
+    for segment in segments(command)? {
+        let Segment::Placeholder(placeholder) = segment else { continue };
+        match result.iter_mut().find(|p| p.name == placeholder.name) {
+            Some(existing) => {
+                if existing.default.is_none() {
+                    existing.default = placeholder.default;
+                }
+                if existing.choices.is_empty() {
+                    existing.choices = placeholder.choices;
+                }
+            }
+            None => result.push(placeholder),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Quoting in effect where a placeholder appears in a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteContext {
+    Bare,
+    Single,
+    Double,
+}
+
+/// Replaces each placeholder in `command` with the text returned by
+/// `replace`, which is told the quoting the placeholder appears in.
+pub fn substitute(
+    command: &str,
+    mut replace: impl FnMut(&Placeholder, QuoteContext) -> Result<String, Box<dyn std::error::Error>>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let declared = placeholders(command)?;
+    let mut result = String::with_capacity(command.len());
+    let mut quote: Option<char> = None;
+
+    for segment in segments(command)? {
+        match segment {
+            Segment::Text(text) => {
+                let mut escaped = false;
+                for c in text.chars() {
+                    match quote {
+                        _ if escaped => escaped = false,
+                        Some('\'') if c == '\'' => quote = None,
+                        Some('\'') => {}
+                        _ if c == '\\' => escaped = true,
+                        Some(q) if c == q => quote = None,
+                        None if c == '\'' || c == '"' => quote = Some(c),
+                        _ => {}
+                    }
+                    result.push(c);
+                }
+            }
+            Segment::Placeholder(placeholder) => {
+                let placeholder = declared.iter().find(|p| p.name == placeholder.name).unwrap_or(&placeholder);
+                let context = match quote {
+                    Some('\'') => QuoteContext::Single,
+                    Some(_) => QuoteContext::Double,
+                    None => QuoteContext::Bare,
+                };
+                result.push_str(&replace(placeholder, context)?);
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Substitutes `values` into `command`, falling back to each placeholder's
+/// default. Values are quoted for the context they land in: bare words,
+/// inside single quotes or inside double quotes.
+pub fn render(command: &str, values: &HashMap<String, String>) -> Result<String, Box<dyn std::error::Error>> {
+    substitute(command, |placeholder, context| {
+        let value = value_for(placeholder, values)?;
+        Ok(match context {
+            QuoteContext::Single => value.replace('\'', r"'\''"),
+            QuoteContext::Double => double_quote_escape(value),
+            QuoteContext::Bare => shell_quote(value),
+        })
+    })
+}
+
+/// Substitutes `values` into `text` as given, for scripts whose interpreter
+/// is not a shell.
+pub fn render_raw(text: &str, values: &HashMap<String, String>) -> Result<String, Box<dyn std::error::Error>> {
+    substitute(text, |placeholder, _| Ok(value_for(placeholder, values)?.to_string()))
+}
+
+/// The value for `placeholder`, falling back to its default, checked against
+/// its choices.
+fn value_for<'a>(placeholder: &'a Placeholder, values: &'a HashMap<String, String>) -> Result<&'a str, Box<dyn std::error::Error>> {
+    let value = values
+        .get(&placeholder.name)
+        .or(placeholder.default.as_ref())
+        .ok_or_else(|| format!("Missing value for '{}'", placeholder.name))?;
+    placeholder.validate(value)?;
+    Ok(value)
+}
+
+/// Escapes `value` for use inside shell double quotes.
+pub fn double_quote_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Quotes `value` as a single shell word, leaving simple words unchanged.
+pub fn shell_quote(value: &str) -> String {
+    let safe = !value.is_empty()
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c));
+    if safe {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+/// Parses `key=value` arguments as given to `--arg`.
+pub fn parse_args(args: &[String]) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    args.iter()
+        .map(|arg| {
+            arg.split_once('=')
+                .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+                .ok_or_else(|| format!("Invalid argument '{}': expected key=value", arg).into())
+        })
+        .collect()
+}
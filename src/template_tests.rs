@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use crate::template::{escape_literal, parse_args, placeholders, render, render_raw, shell_quote};
+
+fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+#[test]
+fn test_parse_placeholders() {
+    let found = placeholders("kubectl -n {{ns=default}} logs {{pod}} --tail {{lines:10|100|1000=100}} {{pod}}").unwrap();
+    let names: Vec<&str> = found.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, vec!["ns", "pod", "lines"]);
+    assert_eq!(found[0].default.as_deref(), Some("default"));
+    assert_eq!(found[2].choices, vec!["10", "100", "1000"]);
+    assert_eq!(found[2].default.as_deref(), Some("100"));
+
+    assert!(placeholders("echo {{env:dev|prod=test}}").is_err());
+    assert!(placeholders("echo plain").unwrap().is_empty());
+}
+
+#[test]
+fn test_literal_braces() {
+    // Go templates and unterminated braces are not placeholders
+    let command = "docker ps --format '{{.Names}} {{ json .Ports }}' {{name";
+    assert!(placeholders(command).unwrap().is_empty());
+    assert_eq!(render(command, &HashMap::new()).unwrap(), command);
+
+    let command = r"kubectl get {{kind}} -o go-template='{{range .items}}\{{end}}'";
+    let found = placeholders(command).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(
+        render(command, &values(&[("kind", "pods")])).unwrap(),
+        "kubectl get pods -o go-template='{{range .items}}{{end}}'"
+    );
+}
+
+#[test]
+fn test_render_quotes_values() {
+    let command = "docker logs -f {{container}}";
+    assert_eq!(render(command, &values(&[("container", "web")])).unwrap(), "docker logs -f web");
+    assert_eq!(
+        render(command, &values(&[("container", "a b; rm -rf /")])).unwrap(),
+        "docker logs -f 'a b; rm -rf /'"
+    );
+    assert!(render(command, &HashMap::new()).is_err());
+
+    // Inside quotes the value is escaped for that quoting style
+    assert_eq!(
+        render("git commit -m \"{{msg}}\"", &values(&[("msg", "say \"hi\" $HOME")])).unwrap(),
+        r#"git commit -m "say \"hi\" \$HOME""#
+    );
+    assert_eq!(
+        render("echo '{{msg}}'", &values(&[("msg", "it's")])).unwrap(),
+        "echo 'it'\\''s'"
+    );
+}
+
+#[test]
+fn test_render_raw_keeps_values() {
+    let script = "name = \"{{name}}\"\nprint('{{greeting=hi there}}', name)";
+    assert_eq!(
+        render_raw(script, &values(&[("name", "it's")])).unwrap(),
+        "name = \"it's\"\nprint('hi there', name)"
+    );
+    assert!(render_raw("{{env:dev|prod}}", &values(&[("env", "test")])).is_err());
+}
+
+#[test]
+fn test_render_defaults_and_choices() {
+    let command = "deploy --env {{env:dev|prod=dev}} --replicas {{n=2}}";
+    assert_eq!(render(command, &HashMap::new()).unwrap(), "deploy --env dev --replicas 2");
+    assert_eq!(render(command, &values(&[("env", "prod")])).unwrap(), "deploy --env prod --replicas 2");
+    assert!(render(command, &values(&[("env", "staging")])).is_err());
+}
+
+#[test]
+fn test_shell_quote_and_args() {
+    assert_eq!(shell_quote("path/to-file.txt"), "path/to-file.txt");
+    assert_eq!(shell_quote(""), "''");
+    assert_eq!(shell_quote("it's"), "'it'\\''s'");
+
+    let args = parse_args(&["a=1".to_string(), "b=x=y".to_string()]).unwrap();
+    assert_eq!(args["b"], "x=y");
+    assert!(parse_args(&["novalue".to_string()]).is_err());
+}
+
+#[test]
+fn test_escape_literal_round_trips() {
+    for text in ["{{.Names}} {{name}}", r"printf '\{{x}}'", "{{env:a|b=c}} {{", "plain"] {
+        assert_eq!(render(&escape_literal(text), &HashMap::new()).unwrap(), text);
+    }
+}